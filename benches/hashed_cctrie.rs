@@ -0,0 +1,57 @@
+#![feature(test)]
+
+extern crate cchamt;
+
+extern crate test;
+
+use test::Bencher;
+use std::collections::HashMap;
+use cchamt::HashedTrie;
+
+const RANGE: usize = 10000;
+
+fn keys() -> Vec<String> {
+    (0..RANGE).map(|i| format!("identifier_{}", i)).collect()
+}
+
+#[bench]
+fn bench_10k_get_hashmap(b: &mut Bencher) {
+    let keys = keys();
+    let mut map = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        map.insert(key.clone(), i);
+    }
+    b.iter(|| {
+        for key in &keys {
+            let _g = map.get(key);
+        }
+    });
+}
+
+#[bench]
+fn bench_10k_get_hashed_trie(b: &mut Bencher) {
+    let keys = keys();
+    let mut map = HashedTrie::new();
+    for (i, key) in keys.iter().enumerate() {
+        map.insert(key.clone(), i);
+    }
+    b.iter(|| {
+        for key in &keys {
+            let _g = map.get(key);
+        }
+    });
+}
+
+#[bench]
+fn bench_10k_get_hashed_contiguous_trie(b: &mut Bencher) {
+    let keys = keys();
+    let mut map = HashedTrie::contiguous(24, 8);
+    for (i, key) in keys.iter().enumerate() {
+        map.insert(key.clone(), i);
+    }
+    b.iter(|| {
+        for key in &keys {
+            let _g = map.get(key);
+        }
+    });
+}
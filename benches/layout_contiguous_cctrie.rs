@@ -0,0 +1,78 @@
+#![feature(test)]
+
+extern crate cchamt;
+
+extern crate test;
+extern crate rand;
+
+use test::Bencher;
+use rand::{Rng, thread_rng};
+use cchamt::{ContiguousTrie, Layout};
+
+const RANGE: usize = 100000;
+
+fn build(layout: Layout) -> (ContiguousTrie<usize>, Vec<Vec<u8>>) {
+    let mut trie = ContiguousTrie::<usize>::with_layout(&[4, 4, 4, 4, 4, 4], layout);
+    let mut v: Vec<Vec<u8>> = Vec::new();
+
+    for i in 0..RANGE {
+        let arr = format!("{:024b}", i).into_bytes();
+        trie.insert(i, &arr);
+        v.push(arr);
+    }
+    (trie, v)
+}
+
+fn bench_sequential(b: &mut Bencher, layout: Layout) {
+    let (trie, v) = build(layout);
+
+    b.iter(|| {
+        for arr in &v {
+            let _g = trie.get(arr);
+        }
+    });
+}
+
+fn bench_random(b: &mut Bencher, layout: Layout) {
+    let (trie, mut v) = build(layout);
+    {
+        let slice: &mut [Vec<u8>] = v.as_mut_slice();
+        thread_rng().shuffle(slice);
+    }
+
+    b.iter(|| {
+        for arr in &v {
+            let _g = trie.get(arr);
+        }
+    });
+}
+
+#[bench]
+fn bench_bfs_sequential_get(b: &mut Bencher) {
+    bench_sequential(b, Layout::BreadthFirst);
+}
+
+#[bench]
+fn bench_dfs_sequential_get(b: &mut Bencher) {
+    bench_sequential(b, Layout::DepthFirst);
+}
+
+#[bench]
+fn bench_veb_sequential_get(b: &mut Bencher) {
+    bench_sequential(b, Layout::VanEmdeBoas);
+}
+
+#[bench]
+fn bench_bfs_random_get(b: &mut Bencher) {
+    bench_random(b, Layout::BreadthFirst);
+}
+
+#[bench]
+fn bench_dfs_random_get(b: &mut Bencher) {
+    bench_random(b, Layout::DepthFirst);
+}
+
+#[bench]
+fn bench_veb_random_get(b: &mut Bencher) {
+    bench_random(b, Layout::VanEmdeBoas);
+}
@@ -24,3 +24,10 @@ impl<T> Allocator<T> {
         unsafe {&mut *self.buf.offset(i as isize)}
     }
 }
+
+// the arena is freed whole, the objects in it are not dropped
+impl<T> Drop for Allocator<T> {
+    fn drop(&mut self) {
+        unsafe { libc::free(self.buf as *mut libc::c_void) };
+    }
+}
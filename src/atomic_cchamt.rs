@@ -0,0 +1,206 @@
+/// Lock-free Cache Conscious Hash Trie for word sized values
+///
+/// Every slot of memory is a single AtomicUsize: an interior slot holds the offset of its
+/// children and a leaf holds the value itself, or EMPTY when the key is absent. Inserts are a
+/// swap, gets are a plain load and nothing is ever locked.
+///
+/// The interior slots are all materialized by `new` in breadth-first order. The leaves are kept in
+/// fixed size chunks which are created on first touch and published with a CAS, a chunk is never
+/// moved or freed before the trie is dropped.
+
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+use node::{compute_index, walk_key};
+
+/// The word marking an empty leaf, so usize::MAX itself cannot be stored
+pub const EMPTY: usize = usize::MAX;
+
+const CHUNK_SIZE: usize = 4096;
+
+/// Core Data structure
+#[derive(Debug)]
+pub struct AtomicContiguousTrie {
+    chunks: Vec<AtomicPtr<AtomicUsize>>,
+    capacity: usize,
+    key_length: usize,
+    key_segment_size: usize,
+}
+
+fn new_chunk() -> *mut AtomicUsize {
+    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+    for _ in 0..CHUNK_SIZE {
+        chunk.push(AtomicUsize::new(EMPTY));
+    }
+    Box::into_raw(chunk.into_boxed_slice()) as *mut AtomicUsize
+}
+
+unsafe fn free_chunk(chunk: *mut AtomicUsize) {
+    let _b = Box::from_raw(::std::slice::from_raw_parts_mut(chunk, CHUNK_SIZE) as *mut [AtomicUsize]);
+}
+
+#[inline(always)]
+fn to_option(word: usize) -> Option<usize> {
+    if word == EMPTY { None } else { Some(word) }
+}
+
+#[inline(always)]
+fn to_word(value: Option<usize>) -> usize {
+    match value {
+        Some(v) => {
+            assert!(v != EMPTY, "usize::MAX is reserved for empty slots");
+            v
+        }
+        None => EMPTY,
+    }
+}
+
+// Contiguous store all the nodes contiguous with the sequential order of key
+impl AtomicContiguousTrie {
+    pub fn new(key_length: usize, key_segment_size: usize) -> Self {
+        assert_eq!(key_length % key_segment_size, 0);
+
+        // capacity = summation of KEY_LEN^1 to KEY_LEN^(KEY_LEN/KEY_GROUP), leaves included
+        let levels = key_length / key_segment_size;
+        let array_length = usize::pow(2, key_segment_size as u32);
+        let mut capacity = 0;
+        let mut multitude = array_length;
+        for _ in 0..levels {
+            capacity += multitude;
+            multitude *= array_length;
+        }
+        let mut chunks = Vec::with_capacity((capacity + CHUNK_SIZE - 1) / CHUNK_SIZE);
+        for _ in 0..chunks.capacity() {
+            chunks.push(AtomicPtr::new(ptr::null_mut()));
+        }
+
+        let trie = AtomicContiguousTrie {
+            chunks,
+            capacity,
+            key_length,
+            key_segment_size,
+        };
+
+        // init with all nodes that is not leaf, the children of slot i start at (i + 1) * array_length
+        let nodes_length = capacity - multitude / array_length;
+        for i in 0..nodes_length {
+            trie.slot_for_write(i).store((i + 1) * array_length, Ordering::Relaxed);
+        }
+        trie
+    }
+
+    // return the slot if its chunk has been created
+    #[inline(always)]
+    fn slot(&self, index: usize) -> Option<&AtomicUsize> {
+        let chunk = self.chunks[index / CHUNK_SIZE].load(Ordering::Acquire);
+        if chunk.is_null() {
+            None
+        } else {
+            Some(unsafe { &*chunk.offset((index % CHUNK_SIZE) as isize) })
+        }
+    }
+
+    // return the slot, creating its chunk first if needed, a thread losing the CAS frees its
+    // own chunk and uses the winner's
+    #[inline(always)]
+    fn slot_for_write(&self, index: usize) -> &AtomicUsize {
+        if let Some(slot) = self.slot(index) {
+            return slot;
+        }
+        let directory = &self.chunks[index / CHUNK_SIZE];
+        let chunk = new_chunk();
+        let chunk = match directory.compare_exchange(ptr::null_mut(), chunk, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => chunk,
+            Err(winner) => {
+                unsafe { free_chunk(chunk); }
+                winner
+            }
+        };
+        unsafe { &*chunk.offset((index % CHUNK_SIZE) as isize) }
+    }
+
+    // key should be 1-1 mapping to self memory array, every interior slot exists so the walk
+    // always takes levels - 1 hops
+    #[inline(always)]
+    fn key2index(&self, key: &[u8]) -> usize {
+        let levels = self.key_length / self.key_segment_size;
+        walk_key(key, compute_index(key, self.key_segment_size), |_| self.key_segment_size, |index, depth| {
+            if depth + 1 < levels { Some(self.slot(index).unwrap().load(Ordering::Relaxed)) } else { None }
+        }).0
+    }
+
+    // insert the entry and return the value it replaced
+    pub fn insert(&self, value: usize, key: &[u8]) -> Option<usize> {
+        let word = to_word(Some(value));
+        to_option(self.slot_for_write(self.key2index(key)).swap(word, Ordering::AcqRel))
+    }
+
+    #[inline(always)]
+    pub fn contain(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    #[inline(always)]
+    pub fn get(&self, key: &[u8]) -> Option<usize> {
+        self.slot(self.key2index(key)).and_then(|slot| to_option(slot.load(Ordering::Acquire)))
+    }
+
+    // remove the entry and return its value
+    pub fn remove(&self, key: &[u8]) -> Option<usize> {
+        self.slot(self.key2index(key)).and_then(|slot| to_option(slot.swap(EMPTY, Ordering::AcqRel)))
+    }
+
+    // add delta to the value of a present entry and return the previous value,
+    // an absent entry is left absent and None is returned. The sum wraps around modulo EMPTY,
+    // the number of storable values, so it skips the reserved EMPTY: EMPTY - 1 plus 1 is 0
+    pub fn fetch_add(&self, key: &[u8], delta: usize) -> Option<usize> {
+        let slot = match self.slot(self.key2index(key)) {
+            Some(slot) => slot,
+            None => return None,
+        };
+        let mut current = slot.load(Ordering::Acquire);
+        loop {
+            if current == EMPTY {
+                return None;
+            }
+            // 2^64 is 1 modulo EMPTY, so an overflow carries one back in
+            let new = match current.overflowing_add(delta) {
+                (EMPTY, false) => 0,
+                (sum, overflow) => sum + overflow as usize,
+            };
+            match slot.compare_exchange_weak(current, new, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return Some(current),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    // replace the entry with new if it is currently equal to current, where None means absent,
+    // return Ok with the previous value on success and Err with the actual value otherwise
+    pub fn compare_exchange(&self, key: &[u8], current: Option<usize>, new: Option<usize>) -> Result<Option<usize>, Option<usize>> {
+        let (current, new) = (to_word(current), to_word(new));
+        let index = self.key2index(key);
+        let slot = if new == EMPTY {
+            match self.slot(index) {
+                Some(slot) => slot,
+                None => return if current == EMPTY { Ok(None) } else { Err(None) },
+            }
+        } else {
+            self.slot_for_write(index)
+        };
+        slot.compare_exchange(current, new, Ordering::AcqRel, Ordering::Acquire)
+            .map(to_option)
+            .map_err(to_option)
+    }
+}
+
+impl Drop for AtomicContiguousTrie {
+    fn drop(&mut self) {
+        for directory in &self.chunks {
+            let chunk = directory.load(Ordering::Relaxed);
+            if !chunk.is_null() {
+                unsafe { free_chunk(chunk); }
+            }
+        }
+    }
+}
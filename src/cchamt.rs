@@ -8,29 +8,74 @@
 /// The benchmark is in:
 /// https://github.com/chichunchen/concurrent-cache-conscious-hamt-in-rust/blob/layout/Benchmark.ipynb
 
+use std::cmp;
+use std::io;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
-pub trait TrieData: Clone + Copy + Eq + PartialEq {}
+use mmap_cchamt::{self, MappedContiguousTrie, PlainData};
+use mutex_cchamt::MutexContiguousTrie;
+use node::{compute_index, walk_key, Change, Iter, SubTrie, TrieData};
+use rwlock_cchamt::RwContiguousTrie;
 
-impl<T> TrieData for T where T: Clone + Copy + Eq + PartialEq {}
+/// How the interior nodes of a ContiguousTrie are allocated
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Allocation {
+    /// every interior node is materialized up front in breadth-first order
+    Eager,
+    /// interior blocks are created on first touch and appended to the free region at the end of memory
+    Lazy,
+}
+
+/// The order in which the interior blocks of an eagerly allocated ContiguousTrie are packed.
+/// The root block always sits at offset 0 and the leaf blocks always follow the interior ones
+/// in key order, so the leaf region can keep growing on demand.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Layout {
+    /// level by level, the original layout
+    BreadthFirst,
+    /// every block is followed by the subtrees of its children (preorder)
+    DepthFirst,
+    /// recursively split the block levels in half and store the top half before each bottom subtree
+    VanEmdeBoas,
+}
 
 /// Private Functions for this module
-/// compute the depth in the trie using the array index of trie.memory
-// TODO bug here
-#[inline(always)]
-fn get_depth(key_group: usize, index: usize) -> usize {
-    let mut depth = 0;
-    let key_length = u32::pow(2, key_group as u32);
-    let mut multitude = key_length;
-    let mut compare = multitude;
-
-    while index >= compare as usize {
-        depth += 1;
-        multitude *= key_length;
-        compare += multitude;
-    }
-    depth
+/// push the blocks, as (depth, ordinal in key order), of the subtree rooted at block (depth, k)
+/// spanning `height` block levels in van Emde Boas order
+fn van_emde_boas_order(segments: &[usize], depth: usize, k: usize, height: usize, order: &mut Vec<(usize, usize)>) {
+    if height == 1 {
+        order.push((depth, k));
+        return;
+    }
+    let top = height / 2;
+    van_emde_boas_order(segments, depth, k, top, order);
+    let mut fanout = 1;
+    for d in depth..(depth + top) {
+        fanout *= usize::pow(2, segments[d] as u32);
+    }
+    for sub in 0..fanout {
+        van_emde_boas_order(segments, depth + top, k * fanout + sub, height - top, order);
+    }
+}
+
+/// push the blocks of the subtree rooted at block (depth, k) in depth first order
+fn depth_first_order(segments: &[usize], depth: usize, k: usize, height: usize, order: &mut Vec<(usize, usize)>) {
+    order.push((depth, k));
+    if height > 1 {
+        let array_length = usize::pow(2, segments[depth] as u32);
+        for sub in 0..array_length {
+            depth_first_order(segments, depth + 1, k * array_length + sub, height - 1, order);
+        }
+    }
+}
+
+/// push the segment_size binary digits of a slot in its block, most significant first
+fn push_slot_digits(slot: usize, segment_size: usize, key: &mut Vec<u8>) {
+    for i in (0..segment_size).rev() {
+        key.push(if (slot >> i) & 1 == 1 { b'1' } else { b'0' });
+    }
 }
 
 /// Core Data structure
@@ -38,94 +83,148 @@ fn get_depth(key_group: usize, index: usize) -> usize {
 pub struct ContiguousTrie<T: TrieData> {
     memory: Vec<Option<SubTrie<T>>>,
     key_length: usize,
-    key_segment_size: usize,
-}
-
-
-#[derive(Clone, Eq, PartialEq, Debug)]
-pub struct SubTrie<T: TrieData> {
-    pub data: Option<T>,
-    depth: usize,
-    children_offset: Option<usize>,    // the start position in allocator that place the array in hash trie
+    segments: Vec<usize>,       // the stride of each level, from the top level down to the leaves
+    allocation: Allocation,
+    layout: Layout,             // the packing of the interior blocks when allocation is Eager
 }
 
 // Contiguous store all the nodes contiguous with the sequential order of key
 impl<T: TrieData> ContiguousTrie<T> {
     pub fn new(key_length: usize, key_segment_size: usize) -> Self {
+        ContiguousTrie::with_allocation(key_length, key_segment_size, Allocation::Eager)
+    }
+
+    pub fn with_allocation(key_length: usize, key_segment_size: usize, allocation: Allocation) -> Self {
         assert_eq!(key_length % key_segment_size, 0);
+        ContiguousTrie::with_segments(&vec![key_segment_size; key_length / key_segment_size], allocation)
+    }
+
+    // build a trie whose level i consumes segments[i] bits of the key, for instance [16, 8, 8]
+    // gives a direct-indexed top table of 2^16 entries over two small levels (DIR-24-8 style)
+    pub fn with_segments(segments: &[usize], allocation: Allocation) -> Self {
+        assert!(!segments.is_empty() && segments.iter().all(|&s| s > 0));
 
-        let mut memory: Vec<Option<SubTrie<T>>>;
-        // init with all nodes that is not leaf
-        // length = summation of KEY_LEN^1 to KEY_LEN^(KEY_LEN/KEY_GROUP-1)
-        {
-            let mut nodes_length = 0;
-            let array_length = usize::pow(2, key_segment_size as u32);
-            let mut multitude = array_length;
-            for _ in 0..(key_length / key_segment_size - 1) {
-                nodes_length += multitude;
-                multitude *= array_length;
+        if allocation == Allocation::Eager {
+            return ContiguousTrie::with_layout(segments, Layout::BreadthFirst);
+        }
+
+        // only the block of the root's children exists, the rest is created by insert
+        ContiguousTrie {
+            memory: vec![None; usize::pow(2, segments[0] as u32)],
+            key_length: segments.iter().sum(),
+            segments: segments.to_vec(),
+            allocation,
+            layout: Layout::BreadthFirst,
+        }
+    }
+
+    // build an eagerly allocated trie whose interior blocks are packed in the given layout,
+    // key2index only follows children_offset so lookups work unchanged on every layout
+    pub fn with_layout(segments: &[usize], layout: Layout) -> Self {
+        assert!(!segments.is_empty() && segments.iter().all(|&s| s > 0));
+
+        // the interior blocks form a tree with one block level per interior depth
+        let height = segments.len() - 1;
+        let mut order = vec![];
+        if height > 0 {
+            match layout {
+                Layout::BreadthFirst => {
+                    let mut level_length = 1;
+                    for depth in 0..height {
+                        for k in 0..level_length {
+                            order.push((depth, k));
+                        }
+                        level_length *= usize::pow(2, segments[depth] as u32);
+                    }
+                }
+                Layout::DepthFirst => depth_first_order(segments, 0, 0, height, &mut order),
+                Layout::VanEmdeBoas => van_emde_boas_order(segments, 0, 0, height, &mut order),
             }
-//            println!("nl {}", nodes_length);
-            memory = Vec::with_capacity(nodes_length);
+        }
 
-            for i in 0..nodes_length {
-                memory.push(Some(SubTrie {
+        // assign the offset of every block following the order
+        let mut offsets: Vec<Vec<usize>> = vec![];
+        let mut level_length = 1;
+        for depth in 0..height {
+            offsets.push(vec![0; level_length]);
+            level_length *= usize::pow(2, segments[depth] as u32);
+        }
+        let mut nodes_length = 0;
+        for &(depth, k) in &order {
+            offsets[depth][k] = nodes_length;
+            nodes_length += usize::pow(2, segments[depth] as u32);
+        }
+//        println!("nl {}", nodes_length);
+
+        // init with all nodes that is not leaf, the leaf blocks start right after them
+        let mut memory: Vec<Option<SubTrie<T>>> = vec![None; nodes_length];
+        for &(depth, k) in &order {
+            let array_length = usize::pow(2, segments[depth] as u32);
+            for slot in 0..array_length {
+                let child = k * array_length + slot;
+                let children_offset = if depth + 1 < height {
+                    offsets[depth + 1][child]
+                } else {
+                    nodes_length + child * usize::pow(2, segments[depth + 1] as u32)
+                };
+                memory[offsets[depth][k] + slot] = Some(SubTrie {
                     data: None,
-                    depth: get_depth(key_segment_size as usize, i),
-                    children_offset: Some((i + 1) * array_length as usize),
-                }));
-//                println!("co {} {}", i, (i + 1) * array_length as usize);
+                    depth,
+                    children_offset: Some(children_offset),
+                });
             }
         }
 
         ContiguousTrie {
             memory,
-            key_length,
-            key_segment_size,
+            key_length: segments.iter().sum(),
+            segments: segments.to_vec(),
+            allocation: Allocation::Eager,
+            layout,
         }
     }
 
-    // return the index in the first <= 4 bits
-    // for instances: 0000 0000 -> 0
-    #[inline(always)]
-    fn compute_index(&self, key: &[u8]) -> usize {
-        let mut id = 0;
-        let length = if key.len() > self.key_segment_size { self.key_segment_size } else { key.len() };
-        for i in 0..length {
-            let temp = key[i] as usize - '0' as usize;
-            id += temp << (length - i - 1);
-        }
-        return id as usize;
+    // number of slots currently allocated in memory, both interior nodes and leaves
+    pub fn memory_len(&self) -> usize {
+        self.memory.len()
+    }
+
+    // write memory as a versioned binary image that open_mmap can serve without deserializing
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> where T: PlainData {
+        mmap_cchamt::write_image(path, self.key_length, &self.segments, &self.memory)
+    }
+
+    // map an image written by write_to read-only, get and contain read the mapping directly
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> io::Result<MappedContiguousTrie<T>> where T: PlainData {
+        MappedContiguousTrie::open(path)
     }
 
     // key should be 1-1 mapping to self memory array
     #[inline(always)]
     fn key2index(&self, key: &[u8]) -> (usize, usize) {
-        let mut current_index = self.compute_index(key);
-        let mut key_start = 0;
-        let mut depth = 0;
-        while self.memory.len() > current_index && self.memory[current_index].is_some() {
-//            println!("comp_index {} ci {} {}", self.compute_index(&key[key_start..]), current_index, self.memory.len());
-            match &self.memory[current_index] {
-                Some(a) => {
-                    match a.children_offset {
-                        Some(b) => {
-                            key_start += self.key_segment_size;
-                            depth += 1;
-                            current_index = b + self.compute_index(&key[key_start..]);
-                        }
-                        None => break,
-                    }
-                }
-                None => break,
-            }
-        }
-        (current_index, depth)
+        walk_key(key, compute_index(key, self.segments[0]), |depth| self.segments[depth],
+                 |index, _| self.memory.get(index).and_then(|a| a.as_ref()).and_then(|a| a.children_offset))
     }
 
-    // insert the entry to hash trie
-    pub fn insert(&mut self, value: T, key: &[u8]) {
-        let index_depth_pair = self.key2index(key);
+    // insert the entry to hash trie and return the value it replaced
+    pub fn insert(&mut self, value: T, key: &[u8]) -> Option<T> {
+        let mut index_depth_pair = self.key2index(key);
+        if self.allocation == Allocation::Lazy {
+            // key2index stopped at an empty interior slot, so create the blocks down to the leaf level
+            let mut key_start: usize = self.segments[..index_depth_pair.1].iter().sum();
+            while index_depth_pair.1 < self.segments.len() - 1 {
+                let depth = index_depth_pair.1;
+                let offset = self.memory.len();
+                self.memory[index_depth_pair.0] = Some(SubTrie {
+                    data: None,
+                    depth,
+                    children_offset: Some(offset),
+                });
+                self.memory.resize(offset + usize::pow(2, self.segments[depth + 1] as u32), None);
+                key_start += self.segments[depth];
+                index_depth_pair = (offset + compute_index(&key[key_start..], self.segments[depth + 1]), depth + 1);
+            }
+        }
 //        println!("debug {} {}", index_depth_pair, self.memory.len());
         if index_depth_pair.0 >= self.memory.len() {
             let push_amount = index_depth_pair.0 - self.memory.len() + 1;
@@ -133,14 +232,13 @@ impl<T: TrieData> ContiguousTrie<T> {
                 self.memory.push(None);
             }
         }
-        if self.memory[index_depth_pair.0].is_some() {
-            assert!(false);
-        }
+        let old = self.memory[index_depth_pair.0].as_ref().and_then(|a| a.data);
         self.memory[index_depth_pair.0] = Some(SubTrie {
             data: Some(value),
             depth: index_depth_pair.1,
             children_offset: None,
         });
+        old
     }
 
     // return true if the key entry exists
@@ -158,6 +256,25 @@ impl<T: TrieData> ContiguousTrie<T> {
         }
     }
 
+    // return the depth of the entry stored by the key, the top level is depth 0
+    pub fn depth_of(&self, key: &[u8]) -> Option<usize> {
+        let index_depth_pair = self.key2index(key);
+        match self.memory.get(index_depth_pair.0) {
+            Some(Some(a)) if a.data.is_some() => Some(a.depth),
+            _ => None,
+        }
+    }
+
+    // return the deepest depth among the stored entries, which bounds the hops of a lookup
+    pub fn max_depth(&self) -> usize {
+        self.memory.iter()
+            .filter_map(|a| a.as_ref())
+            .filter(|a| a.data.is_some())
+            .map(|a| a.depth)
+            .max()
+            .unwrap_or(0)
+    }
+
     // return the value in the given key and wrap it with an Option
     #[inline(always)]
     pub fn get(&self, key: &[u8]) -> Option<T> {
@@ -172,6 +289,254 @@ impl<T: TrieData> ContiguousTrie<T> {
             None => None,
         }
     }
+
+    // remove the entry stored by the key and return its value, the blocks stay allocated
+    pub fn remove(&mut self, key: &[u8]) -> Option<T> {
+        let index_depth_pair = self.key2index(key);
+        let old = match self.memory.get(index_depth_pair.0) {
+            Some(Some(a)) if a.children_offset.is_none() => a.data,
+            _ => None,
+        };
+        if old.is_some() {
+            self.memory[index_depth_pair.0] = None;
+        }
+        old
+    }
+
+    pub fn key_length(&self) -> usize {
+        self.key_length
+    }
+
+    // iterate the entries in key order, the keys are binary digit strings of key_length bits
+    pub fn iter(&self) -> Iter<T> {
+        Iter::new(&self.memory, &self.segments, usize::pow(2, self.segments[0] as u32), 0)
+    }
+
+    // hand memory out if it has the breadth-first layout of uniform segments the locked tries
+    // use, which means node i has its children at (i + 1) * 2^segment, otherwise give self back
+    pub(crate) fn into_breadth_first(self) -> Result<(Vec<Option<SubTrie<T>>>, usize, usize), Self> {
+        let segment_size = self.segments[0];
+        if self.allocation == Allocation::Eager && self.layout == Layout::BreadthFirst
+            && self.segments.iter().all(|&s| s == segment_size) {
+            Ok((self.memory, self.key_length, segment_size))
+        } else {
+            Err(self)
+        }
+    }
+
+    // the inverse of into_breadth_first
+    pub(crate) fn from_breadth_first(memory: Vec<Option<SubTrie<T>>>, key_length: usize, key_segment_size: usize) -> Self {
+        ContiguousTrie {
+            memory,
+            key_length,
+            segments: vec![key_segment_size; key_length / key_segment_size],
+            allocation: Allocation::Eager,
+            layout: Layout::BreadthFirst,
+        }
+    }
+
+    // the largest segment size that divides every level, the uniform trie any key of self fits in
+    pub(crate) fn common_segment_size(&self) -> usize {
+        fn gcd(a: usize, b: usize) -> usize {
+            if b == 0 { a } else { gcd(b, a % b) }
+        }
+        self.segments.iter().fold(0, |a, &b| gcd(a, b))
+    }
+
+    // combine self and other block by block, f gives the value of a key from the values on both
+    // sides. The result starts as a copy of self's memory. A subtree on one side only is kept or
+    // cleared whole by keep_left / keep_right, the ones only other has are copied block by block
+    // to the end of memory, so f is only called on the leaf blocks both tries have
+    pub(crate) fn zip_with<F>(&self, other: &ContiguousTrie<T>, keep_left: bool, keep_right: bool, f: &mut F) -> ContiguousTrie<T>
+        where F: FnMut(&[u8], Option<T>, Option<T>) -> Option<T> {
+        assert_eq!(self.segments, other.segments, "the tries do not split keys the same way");
+        let mut result = ContiguousTrie {
+            memory: self.memory.clone(),
+            key_length: self.key_length,
+            segments: self.segments.clone(),
+            allocation: self.allocation,
+            layout: self.layout,
+        };
+        result.zip_block(0, other, 0, 0, &mut vec![], keep_left, keep_right, f);
+        result
+    }
+
+    // fold other into self walking both in lockstep, the subtrees only other has are copied over
+    // as whole blocks and resolve(key, ours, theirs) gives the value of a key stored on both sides.
+    // Both tries must split keys the same way, their allocations and layouts may differ
+    pub fn merge<F: Fn(&[u8], T, T) -> T>(&mut self, other: ContiguousTrie<T>, resolve: F) {
+        assert_eq!(self.segments, other.segments, "the tries do not split keys the same way");
+        self.zip_block(0, &other, 0, 0, &mut vec![], true, true, &mut |key, a, b| match (a, b) {
+            (Some(a), Some(b)) => Some(resolve(key, a, b)),
+            (a, b) => a.or(b),
+        });
+    }
+
+    // key holds the digits leading to the block
+    fn zip_block<F>(&mut self, offset: usize, other: &ContiguousTrie<T>, other_offset: usize, depth: usize,
+                    key: &mut Vec<u8>, keep_left: bool, keep_right: bool, f: &mut F)
+        where F: FnMut(&[u8], Option<T>, Option<T>) -> Option<T> {
+        let segment_size = self.segments[depth];
+        let key_start = key.len();
+        for slot in 0..usize::pow(2, segment_size as u32) {
+            let a = self.memory.get(offset + slot).cloned().unwrap_or(None);
+            let b = other.memory.get(other_offset + slot).cloned().unwrap_or(None);
+            let a_children = a.as_ref().and_then(|a| a.children_offset);
+            let b_children = b.as_ref().and_then(|b| b.children_offset);
+            match (a_children, b_children) {
+                (Some(a_children), Some(b_children)) => {
+                    push_slot_digits(slot, segment_size, key);
+                    self.zip_block(a_children, other, b_children, depth + 1, key, keep_left, keep_right, f);
+                    key.truncate(key_start);
+                }
+                (Some(a_children), None) => if !keep_left {
+                    self.clear_block(a_children, depth + 1);
+                },
+                (None, Some(b_children)) => if keep_right {
+                    let children_offset = self.copy_block(other, b_children, depth + 1);
+                    self.memory[offset + slot] = Some(SubTrie {
+                        data: None,
+                        depth,
+                        children_offset: Some(children_offset),
+                    });
+                },
+                (None, None) => {
+                    let data = match (a.and_then(|a| a.data), b.and_then(|b| b.data)) {
+                        (None, None) => continue,
+                        (a, b) => {
+                            push_slot_digits(slot, segment_size, key);
+                            let data = f(key, a, b);
+                            key.truncate(key_start);
+                            data
+                        }
+                    };
+                    if offset + slot >= self.memory.len() {
+                        if data.is_none() {
+                            continue;
+                        }
+                        self.memory.resize(offset + slot + 1, None);
+                    }
+                    self.memory[offset + slot] = data.map(|data| SubTrie {
+                        data: Some(data),
+                        depth,
+                        children_offset: None,
+                    });
+                }
+            }
+        }
+    }
+
+    // the changes that turn old into new, in key order. Both tries must split keys the same way,
+    // and a leaf block whose slots are equal on both sides is skipped with one slice comparison
+    pub fn diff(old: &ContiguousTrie<T>, new: &ContiguousTrie<T>) -> Vec<Change<T>> {
+        assert_eq!(old.segments, new.segments, "the tries do not split keys the same way");
+        let mut changes = vec![];
+        old.diff_block(Some(0), new, Some(0), 0, &mut vec![], &mut changes);
+        changes
+    }
+
+    // the slots of a block, cut short where memory ends since the missing slots are all None
+    fn block(&self, offset: usize, width: usize) -> &[Option<SubTrie<T>>] {
+        let end = cmp::min(offset + width, self.memory.len());
+        &self.memory[cmp::min(offset, end)..end]
+    }
+
+    // a block missing on one side, as an offset of None, compares as a block of empty slots
+    fn diff_block(&self, offset: Option<usize>, new: &ContiguousTrie<T>, new_offset: Option<usize>, depth: usize,
+                  key: &mut Vec<u8>, changes: &mut Vec<Change<T>>) {
+        let segment_size = self.segments[depth];
+        let width = usize::pow(2, segment_size as u32);
+        if depth == self.segments.len() - 1 {
+            if let (Some(a), Some(b)) = (offset, new_offset) {
+                if self.block(a, width) == new.block(b, width) {
+                    return;
+                }
+            }
+        }
+
+        let key_start = key.len();
+        for slot in 0..width {
+            let a = offset.and_then(|a| self.memory.get(a + slot)).and_then(|a| a.as_ref());
+            let b = new_offset.and_then(|b| new.memory.get(b + slot)).and_then(|b| b.as_ref());
+            let a_children = a.and_then(|a| a.children_offset);
+            let b_children = b.and_then(|b| b.children_offset);
+            push_slot_digits(slot, segment_size, key);
+            if a_children.is_some() || b_children.is_some() {
+                self.diff_block(a_children, new, b_children, depth + 1, key, changes);
+            } else {
+                changes.extend(Change::between(key, a.and_then(|a| a.data), b.and_then(|b| b.data)));
+            }
+            key.truncate(key_start);
+        }
+    }
+
+    // drop every entry under the block, the interior slots stay so an eager trie keeps its shape
+    fn clear_block(&mut self, offset: usize, depth: usize) {
+        for slot in 0..usize::pow(2, self.segments[depth] as u32) {
+            let children_offset = match self.memory.get(offset + slot) {
+                Some(Some(a)) => a.children_offset,
+                _ => continue,
+            };
+            match children_offset {
+                Some(b) => self.clear_block(b, depth + 1),
+                None => self.memory[offset + slot] = None,
+            }
+        }
+    }
+
+    // append a copy of the block of other and of the blocks under it, return its new offset
+    fn copy_block(&mut self, other: &ContiguousTrie<T>, other_offset: usize, depth: usize) -> usize {
+        let offset = self.memory.len();
+        let width = usize::pow(2, self.segments[depth] as u32);
+        self.memory.resize(offset + width, None);
+        for slot in 0..width {
+            self.memory[offset + slot] = match other.memory.get(other_offset + slot) {
+                Some(Some(a)) => match a.children_offset {
+                    Some(b) => Some(SubTrie {
+                        data: None,
+                        depth,
+                        children_offset: Some(self.copy_block(other, b, depth + 1)),
+                    }),
+                    None => Some(a.clone()),
+                },
+                _ => None,
+            };
+        }
+        offset
+    }
+}
+
+// the entries are inserted one by one, overwriting the existing ones
+impl<T: TrieData> Extend<(Vec<u8>, T)> for ContiguousTrie<T> {
+    fn extend<I: IntoIterator<Item=(Vec<u8>, T)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(value, &key);
+        }
+    }
+}
+
+impl<T: TrieData> From<MutexContiguousTrie<T>> for ContiguousTrie<T> {
+    // moves memory, the mutex trie always has the breadth-first layout
+    fn from(trie: MutexContiguousTrie<T>) -> Self {
+        let (memory, key_length, key_segment_size) = trie.into_memory();
+        ContiguousTrie::from_breadth_first(memory, key_length, key_segment_size)
+    }
+}
+
+impl<T: TrieData> From<RwContiguousTrie<T>> for ContiguousTrie<T> {
+    // moves memory when there is a single stripe, otherwise re-inserts the entries
+    fn from(trie: RwContiguousTrie<T>) -> Self {
+        match trie.into_memory() {
+            Ok((memory, key_length, key_segment_size)) => ContiguousTrie::from_breadth_first(memory, key_length, key_segment_size),
+            Err(trie) => {
+                let mut result = ContiguousTrie::new(trie.key_length(), trie.key_segment_size());
+                for stripe in 0..trie.stripes() {
+                    result.extend(trie.stripe_entries(stripe));
+                }
+                result
+            }
+        }
+    }
 }
 
 // TODO should change this to key_length+2, which is {:0key_length+2b}
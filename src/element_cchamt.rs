@@ -0,0 +1,128 @@
+/// Concurrent Cache Conscious Hash Trie with a lock per element, generic over the lock
+///
+/// Blocks are allocated lazily: an interior slot is an atomic pointer to its child block,
+/// installed once by compare and swap and never changed, and only the slots of the leaf blocks
+/// carry a lock. So no lock ever covers more than one slot, and the key space nothing was
+/// inserted into costs nothing, which lets keys as long as new(32, 8) work.
+/// `MutexElementContiguousTrie` and `RwElementContiguousTrie` are this trie over Mutex and RwLock.
+
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use node::{compute_index, TrieData};
+
+/// The lock guarding one leaf slot
+pub trait ElementLock<X>: Sized {
+    fn new(value: X) -> Self;
+
+    // run f on the value under a lock shared with the other readers
+    fn read<R, F: FnOnce(&X) -> R>(&self, f: F) -> R;
+
+    // run f on the value under an exclusive lock
+    fn write<R, F: FnOnce(&mut X) -> R>(&self, f: F) -> R;
+}
+
+enum Block<L> {
+    Interior(Vec<AtomicPtr<Block<L>>>),    // a null pointer is a child block not created yet
+    Leaf(Vec<L>),
+}
+
+impl<L> Drop for Block<L> {
+    fn drop(&mut self) {
+        if let Block::Interior(ref children) = *self {
+            for child in children {
+                let child = child.load(Ordering::Acquire);
+                if !child.is_null() {
+                    unsafe { Box::from_raw(child) };
+                }
+            }
+        }
+    }
+}
+
+/// Core Data structure
+pub struct ElementContiguousTrie<T: TrieData, L: ElementLock<Option<T>>> {
+    root: Block<L>,
+    key_length: usize,
+    key_segment_size: usize,
+    _lock: PhantomData<(T, L)>,    // the trie owns the blocks behind the atomic pointers
+}
+
+impl<T: TrieData, L: ElementLock<Option<T>>> ElementContiguousTrie<T, L> {
+    pub fn new(key_length: usize, key_segment_size: usize) -> Self {
+        assert_eq!(key_length % key_segment_size, 0);
+        let root = ElementContiguousTrie::<T, L>::block(0, key_length / key_segment_size, key_segment_size);
+        ElementContiguousTrie {
+            root,
+            key_length,
+            key_segment_size,
+            _lock: PhantomData,
+        }
+    }
+
+    // an empty block of the given depth, the last of the levels holds the leaves
+    fn block(depth: usize, levels: usize, key_segment_size: usize) -> Block<L> {
+        let array_length = usize::pow(2, key_segment_size as u32);
+        if depth + 1 == levels {
+            Block::Leaf((0..array_length).map(|_| L::new(None)).collect())
+        } else {
+            Block::Interior((0..array_length).map(|_| AtomicPtr::new(ptr::null_mut())).collect())
+        }
+    }
+
+    pub fn key_length(&self) -> usize {
+        self.key_length
+    }
+
+    // return the lock of the leaf slot of the key, the missing blocks on the way are created
+    // when create is set, otherwise the key is absent
+    #[inline(always)]
+    fn leaf(&self, key: &[u8], create: bool) -> Option<&L> {
+        let levels = self.key_length / self.key_segment_size;
+        let mut block = &self.root;
+        let mut key_start = 0;
+        let mut depth = 0;
+        loop {
+            let index = compute_index(&key[key_start..], self.key_segment_size);
+            let child = match *block {
+                Block::Leaf(ref slots) => return Some(&slots[index]),
+                Block::Interior(ref children) => &children[index],
+            };
+            let mut child_ptr = child.load(Ordering::Acquire);
+            if child_ptr.is_null() {
+                if !create {
+                    return None;
+                }
+                // the loser of a race frees its block and follows the winner's
+                let new = Box::into_raw(Box::new(ElementContiguousTrie::<T, L>::block(depth + 1, levels, self.key_segment_size)));
+                let old = child.compare_and_swap(ptr::null_mut(), new, Ordering::AcqRel);
+                if old.is_null() {
+                    child_ptr = new;
+                } else {
+                    unsafe { Box::from_raw(new) };
+                    child_ptr = old;
+                }
+            }
+            block = unsafe { &*child_ptr };
+            key_start += self.key_segment_size;
+            depth += 1;
+        }
+    }
+
+    // insert the entry and return the value it replaced, only the slot of the key is locked
+    pub fn insert(&self, value: T, key: &[u8]) -> Option<T> {
+        self.leaf(key, true).unwrap().write(|slot| mem::replace(slot, Some(value)))
+    }
+
+    #[inline(always)]
+    pub fn contain(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    #[inline(always)]
+    pub fn get(&self, key: &[u8]) -> Option<T> {
+        self.leaf(key, false).and_then(|a| a.read(|slot| *slot))
+    }
+}
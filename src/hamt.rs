@@ -1,16 +1,25 @@
 /// The very basic hash trie implementation
 /// This file is only for learning how to implement hash trie in Rust
+///
+/// Every node only stores the children that exist: a bitmap marks which of the CHILDREN child
+/// indices are present, and the children sit in a dense array in index order, so child i is at
+/// the number of set bits below bit i.
+///
+/// Keys may have any length. A node consumes KEY_GROUP digits of the key, and a final group of
+/// fewer digits leads to a leaf child under an index reserved for its length, so "01" and "0100"
+/// never share a node.
 
-pub trait TrieData: Clone + Copy + Eq + PartialEq {}
-
-impl<T> TrieData for T where T: Clone + Copy + Eq + PartialEq {}
+use std::ptr;
 
+use cchamt::{Allocation, ContiguousTrie};
+use node::{Change, TrieData};
 
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct Trie<T: TrieData> {
     pub data: Option<T>,
     depth: u32,
-    children: Vec<Option<Box<Trie<T>>>>,
+    bitmap: u32,                    // bit i is set when child i exists
+    children: Vec<Box<Trie<T>>>,    // the existing children in the order of their index
 }
 
 
@@ -22,55 +31,107 @@ pub enum IndexStatus {
 }
 
 const KEY_LEN: usize = 16;
-const KEY_GROUP: usize = 4;
+pub(crate) const KEY_GROUP: usize = 4;
+// 2^KEY_GROUP indices for the whole groups, then 2^r for each length r < KEY_GROUP of a final group
+pub(crate) const CHILDREN: usize = 30;
 
 
-// index is the sum of binary in a group
-fn compute_index(key: &[u8]) -> usize {
+// index is the sum of binary in a group, a final group of r < KEY_GROUP digits is moved past the
+// whole groups into the 2^r indices reserved for its length: 16-17 for 1 digit, 18-21 for 2, 22-29 for 3
+pub(crate) fn compute_index(key: &[u8]) -> usize {
+    debug_assert!(key.len() > 0);
     let mut id = 0;
-    let length = if key.len() > KEY_GROUP { KEY_GROUP } else { key.len() };
+    let length = group_length(key);
     for i in 0..length {
         let temp = key[i] as usize - '0' as usize;
         id += temp << i;
     }
+    if length < KEY_GROUP {
+        id += (1 << KEY_GROUP) + (1 << length) - 2;
+    }
 
     return id as usize;
 }
 
+// the number of key digits consumed by the child index of the key
+#[inline(always)]
+pub(crate) fn group_length(key: &[u8]) -> usize {
+    if key.len() > KEY_GROUP { KEY_GROUP } else { key.len() }
+}
+
+// the inverse of compute_index, push the key digits of a child index and return how many
+pub(crate) fn push_index_digits(index: usize, key: &mut Vec<u8>) -> usize {
+    let (length, id) = if index < (1 << KEY_GROUP) {
+        (KEY_GROUP, index)
+    } else {
+        let mut length = 1;
+        while index >= (1 << KEY_GROUP) + (1 << (length + 1)) - 2 {
+            length += 1;
+        }
+        (length, index - ((1 << KEY_GROUP) + (1 << length) - 2))
+    };
+    for i in 0..length {
+        key.push(if (id >> i) & 1 == 1 { b'1' } else { b'0' });
+    }
+    length
+}
+
 impl<T: TrieData> Trie<T> {
     pub fn new() -> Self {
-        let mut children = Vec::with_capacity(KEY_LEN);
-        for i in 0..KEY_LEN {
-            children.push(None);
+        Trie { data: None, depth: 0, bitmap: 0, children: vec![] }
+    }
+
+    // the position of child index in the dense children array
+    #[inline(always)]
+    fn child_position(&self, index: usize) -> usize {
+        (self.bitmap & ((1 << index) - 1)).count_ones() as usize
+    }
+
+    #[inline(always)]
+    fn child(&self, index: usize) -> Option<&Trie<T>> {
+        if self.bitmap & (1 << index) == 0 {
+            None
+        } else {
+            Some(&self.children[self.child_position(index)])
+        }
+    }
+
+    #[inline(always)]
+    fn child_mut(&mut self, index: usize) -> Option<&mut Trie<T>> {
+        if self.bitmap & (1 << index) == 0 {
+            None
+        } else {
+            let position = self.child_position(index);
+            Some(&mut self.children[position])
         }
-        Trie { data: None, depth: 0, children }
     }
 
+    // the number of values stored in this trie, its own included
     pub fn depth(&self) -> u32 {
         self.depth
     }
 
     // insert a generic value by a key, and the key should be in binary format
+    // return the number of values added, 0 when an existing value is overwritten
     pub fn insert(&mut self, value: T, key: &[u8]) -> u32 {
         if key.len() == 0 {
+            let added = if self.data.is_none() { 1 } else { 0 };
             self.data = Some(value);
-            return 1;
+            self.depth += added;
+            return added;
         } else {
             let index = compute_index(key);
 
             // if the trie has not been created, then create one
-            if self.children[index].is_none() {
+            if self.bitmap & (1 << index) == 0 {
                 // println!("create subtree");
-                self.children[index] = Some(Box::new(Trie::new()));
+                let position = self.child_position(index);
+                self.children.insert(position, Box::new(Trie::new()));
+                self.bitmap |= 1 << index;
             }
-            let value = match key.len() {
-                n if n >= KEY_GROUP => {
-                    self.children[index].as_mut().map(|ref mut a| a.insert(value, &key[KEY_GROUP..])).unwrap_or(0)
-                }
-                _ => 9999,  // TODO value should be Option
-            };
-            self.depth += value;
-            return value;
+            let added = self.child_mut(index).map(|a| a.insert(value, &key[group_length(key)..])).unwrap_or(0);
+            self.depth += added;
+            return added;
         }
     }
 
@@ -107,28 +168,448 @@ impl<T: TrieData> Trie<T> {
             self.data.map(|_| IndexStatus::FullMatch).unwrap_or(IndexStatus::StartingMatch)
         } else {
             let index = compute_index(key);
-            self.children[index].as_ref().map(|ref a| a.index_base(&key[KEY_GROUP..])).unwrap_or(IndexStatus::NoMatch)
+            self.child(index).map(|a| a.index_base(&key[group_length(key)..])).unwrap_or(IndexStatus::NoMatch)
         }
     }
 
+    // visit the values stored along the path of key, as (prefix length, value), shortest prefix first
+    fn walk_prefixes<F: FnMut(usize, T)>(&self, key: &[u8], mut f: F) {
+        let mut node = self;
+        let mut prefix_len = 0;
+        loop {
+            if let Some(data) = node.data {
+                f(prefix_len, data);
+            }
+            // the prefixes ending inside the next group are leaves under the reserved indices
+            for length in 1..KEY_GROUP {
+                if prefix_len + length > key.len() {
+                    break;
+                }
+                if let Some(data) = node.child(compute_index(&key[prefix_len..(prefix_len + length)])).and_then(|a| a.data) {
+                    f(prefix_len + length, data);
+                }
+            }
+            if key.len() - prefix_len < KEY_GROUP {
+                return;
+            }
+            match node.child(compute_index(&key[prefix_len..])) {
+                Some(child) => {
+                    node = child;
+                    prefix_len += KEY_GROUP;
+                }
+                None => return,
+            }
+        }
+    }
+
+    // return the value stored by the longest prefix of key, with the length of that prefix
+    // for instances: with 1010 and 10101100 stored, 101011001111 matches (8, value of 10101100)
+    pub fn longest_prefix_match(&self, key: &[u8]) -> Option<(usize, T)> {
+        let mut result = None;
+        self.walk_prefixes(key, |prefix_len, data| result = Some((prefix_len, data)));
+        result
+    }
+
+    // return the values stored by every prefix of key, shortest prefix first
+    pub fn all_prefix_matches(&self, key: &[u8]) -> Vec<(usize, T)> {
+        let mut result = vec![];
+        self.walk_prefixes(key, |prefix_len, data| result.push((prefix_len, data)));
+        result
+    }
+
     pub fn get_sub_trie<'a>(&'a self, key: &[u8]) -> Option<&'a Trie<T>> {
+        if key.len() == 0 {
+            return Some(&self);
+        }
+        let index = compute_index(key);
+        self.child(index).and_then(|a| a.get_sub_trie(&key[group_length(key)..]))
+    }
+
+    // remove the value stored by the key and return it, the children left without any value are freed
+    pub fn remove(&mut self, key: &[u8]) -> Option<T> {
+        if key.len() == 0 {
+            let old = self.data.take();
+            if old.is_some() {
+                self.depth -= 1;
+            }
+            return old;
+        }
+
         let index = compute_index(key);
-        match key.len() {
-            n if n >= KEY_GROUP => self.children[index].as_ref().and_then(|ref a| a.get_sub_trie(&key[KEY_GROUP..])),
-            _ => Some(&self),
+        let (old, empty) = match self.child_mut(index) {
+            Some(child) => {
+                let old = child.remove(&key[group_length(key)..]);
+                (old, child.depth == 0)
+            }
+            None => return None,
+        };
+        if empty {
+            let position = self.child_position(index);
+            self.children.remove(position);
+            self.bitmap &= !(1 << index);
+        }
+        if old.is_some() {
+            self.depth -= 1;
         }
+        old
     }
 
-    // TODO delete the data in the trie found by the key
+    // delete the data in the trie found by the key
     pub fn delete_key(&mut self, key: &[u8]) {
-        if key.len() == 0 {
-            self.data = None;
-        } else {
-            let index = compute_index(key);
+        self.remove(key);
+    }
+
+    // return all the entries with their binary keys, in the order of the children indices
+    pub fn iter(&self) -> ::std::vec::IntoIter<(Vec<u8>, T)> {
+        let mut entries = vec![];
+        self.collect_entries(&mut vec![], &mut entries);
+        entries.into_iter()
+    }
+
+    fn collect_entries(&self, key: &mut Vec<u8>, entries: &mut Vec<(Vec<u8>, T)>) {
+        if let Some(data) = self.data {
+            entries.push((key.clone(), data));
+        }
+        for index in 0..CHILDREN {
+            if let Some(child) = self.child(index) {
+                let length = key.len();
+                push_index_digits(index, key);
+                child.collect_entries(key, entries);
+                key.truncate(length);
+            }
+        }
+    }
+
+    // combine self and other node by node, f gives the value of a key from the values on both
+    // sides. A child on one side only is cloned whole when keep_left / keep_right and dropped
+    // otherwise, so f is only called on the nodes both tries have
+    pub(crate) fn zip_with<F>(&self, other: &Trie<T>, keep_left: bool, keep_right: bool, f: &mut F) -> Trie<T>
+        where F: FnMut(&[u8], Option<T>, Option<T>) -> Option<T> {
+        self.zip_node(other, keep_left, keep_right, f, &mut vec![])
+    }
+
+    fn zip_node<F>(&self, other: &Trie<T>, keep_left: bool, keep_right: bool, f: &mut F, key: &mut Vec<u8>) -> Trie<T>
+        where F: FnMut(&[u8], Option<T>, Option<T>) -> Option<T> {
+        let data = match (self.data, other.data) {
+            (None, None) => None,
+            (a, b) => f(key, a, b),
+        };
+        let mut result = Trie { data, depth: if data.is_some() { 1 } else { 0 }, bitmap: 0, children: vec![] };
+        for index in 0..CHILDREN {
+            let child = match (self.child(index), other.child(index)) {
+                (Some(a), Some(b)) => {
+                    let length = key.len();
+                    push_index_digits(index, key);
+                    let child = a.zip_node(b, keep_left, keep_right, f, key);
+                    key.truncate(length);
+                    child
+                }
+                (Some(a), None) if keep_left => a.clone(),
+                (None, Some(b)) if keep_right => b.clone(),
+                _ => continue,
+            };
+            if child.depth > 0 {
+                result.depth += child.depth;
+                result.bitmap |= 1 << index;
+                result.children.push(Box::new(child));
+            }
+        }
+        result
+    }
+
+    // fold other into self walking both in lockstep, a child only other has is moved over whole
+    // and resolve(key, ours, theirs) gives the value of a key stored on both sides
+    pub fn merge<F: Fn(&[u8], T, T) -> T>(&mut self, other: Trie<T>, resolve: F) {
+        self.merge_node(other, &resolve, &mut vec![]);
+    }
+
+    fn merge_node<F: Fn(&[u8], T, T) -> T>(&mut self, other: Trie<T>, resolve: &F, key: &mut Vec<u8>) {
+        self.data = match (self.data, other.data) {
+            (Some(a), Some(b)) => Some(resolve(key, a, b)),
+            (a, b) => a.or(b),
+        };
+        let mut children = other.children.into_iter();
+        for index in 0..CHILDREN {
+            if other.bitmap & (1 << index) == 0 {
+                continue;
+            }
+            let child = children.next().unwrap();
+            match self.child_mut(index) {
+                Some(a) => {
+                    let length = key.len();
+                    push_index_digits(index, key);
+                    a.merge_node(*child, resolve, key);
+                    key.truncate(length);
+                }
+                None => {
+                    let position = self.child_position(index);
+                    self.children.insert(position, child);
+                    self.bitmap |= 1 << index;
+                }
+            }
+        }
+        self.depth = if self.data.is_some() { 1 } else { 0 } + self.children.iter().map(|a| a.depth).sum::<u32>();
+    }
+
+    // the changes that turn old into new, in the order of the children indices. A subtree is
+    // skipped when both sides are the very same node, which only happens for a trie against itself
+    // since Trie nodes are never shared
+    pub fn diff(old: &Trie<T>, new: &Trie<T>) -> Vec<Change<T>> {
+        let mut changes = vec![];
+        Trie::diff_node(Some(old), Some(new), &mut vec![], &mut changes);
+        changes
+    }
+
+    fn diff_node(old: Option<&Trie<T>>, new: Option<&Trie<T>>, key: &mut Vec<u8>, changes: &mut Vec<Change<T>>) {
+        if let (Some(a), Some(b)) = (old, new) {
+            if ptr::eq(a, b) {
+                return;
+            }
+        }
+        changes.extend(Change::between(key, old.and_then(|a| a.data), new.and_then(|b| b.data)));
+        for index in 0..CHILDREN {
+            let a = old.and_then(|a| a.child(index));
+            let b = new.and_then(|b| b.child(index));
+            if a.is_some() || b.is_some() {
+                let length = key.len();
+                push_index_digits(index, key);
+                Trie::diff_node(a, b, key, changes);
+                key.truncate(length);
+            }
+        }
+    }
+
+    // walk the subtree of every key starting with prefix, lazily and in the order of the
+    // children indices, yielding at most limit entries
+    pub fn entries_with_prefix<'a>(&'a self, prefix: &[u8], limit: Option<usize>) -> PrefixEntries<'a, T> {
+        // descend the whole groups of the prefix, the digits left over select among the children
+        let mut node = Some(self);
+        let mut whole = 0;
+        while prefix.len() - whole >= KEY_GROUP {
+            node = node.and_then(|a| a.child(compute_index(&prefix[whole..])));
+            whole += KEY_GROUP;
+        }
+        PrefixEntries {
+            stack: node.map(|a| vec![(a, whole, 0)]).unwrap_or(vec![]),
+            key: prefix[..whole].to_vec(),
+            filter: prefix[whole..].to_vec(),
+            limit,
+        }
+    }
+
+    // the full binary keys starting with prefix, for instances for type-ahead search
+    pub fn keys_with_prefix<'a>(&'a self, prefix: &[u8], limit: Option<usize>) -> Keys<'a, T> {
+        Keys { entries: self.entries_with_prefix(prefix, limit) }
+    }
+
+    // the values whose keys start with prefix
+    pub fn values_with_prefix<'a>(&'a self, prefix: &[u8], limit: Option<usize>) -> Values<'a, T> {
+        Values { entries: self.entries_with_prefix(prefix, limit) }
+    }
+}
 
-            if index >= KEY_GROUP {
-                self.children[index].as_mut().map(|ref mut a| a.delete_key(&key[KEY_GROUP..]));
+/// Iterator over the entries under a key prefix, see Trie::entries_with_prefix
+pub struct PrefixEntries<'a, T: TrieData + 'a> {
+    stack: Vec<(&'a Trie<T>, usize, usize)>,    // (node, length of its key, next step) of the open nodes, step 0 is the node's own value and step i + 1 is child i
+    key: Vec<u8>,
+    filter: Vec<u8>,                            // the prefix digits the children of the first node must start with
+    limit: Option<usize>,
+}
+
+impl<'a, T: TrieData> Iterator for PrefixEntries<'a, T> {
+    type Item = (Vec<u8>, T);
+
+    fn next(&mut self) -> Option<(Vec<u8>, T)> {
+        if self.limit == Some(0) {
+            return None;
+        }
+        loop {
+            let (node, key_length, step) = match self.stack.last_mut() {
+                Some(top) => {
+                    let current = *top;
+                    top.2 += 1;
+                    current
+                }
+                None => return None,
+            };
+            let first = self.stack.len() == 1;
+            if step == CHILDREN + 1 {
+                self.stack.pop();
+                continue;
+            }
+
+            self.key.truncate(key_length);
+            if step == 0 {
+                // the first node is shorter than the prefix when some prefix digits are left over
+                if first && !self.filter.is_empty() {
+                    continue;
+                }
+                if let Some(data) = node.data {
+                    self.limit = self.limit.map(|a| a - 1);
+                    return Some((self.key.clone(), data));
+                }
+                continue;
             }
+            if let Some(child) = node.child(step - 1) {
+                let length = push_index_digits(step - 1, &mut self.key);
+                if first && (length < self.filter.len() || !self.key[key_length..].starts_with(&self.filter)) {
+                    continue;
+                }
+                self.stack.push((child, key_length + length, 0));
+            }
+        }
+    }
+}
+
+/// Iterator over the keys under a key prefix, see Trie::keys_with_prefix
+pub struct Keys<'a, T: TrieData + 'a> {
+    entries: PrefixEntries<'a, T>,
+}
+
+impl<'a, T: TrieData> Iterator for Keys<'a, T> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        self.entries.next().map(|a| a.0)
+    }
+}
+
+/// Iterator over the values under a key prefix, see Trie::values_with_prefix
+pub struct Values<'a, T: TrieData + 'a> {
+    entries: PrefixEntries<'a, T>,
+}
+
+impl<'a, T: TrieData> Iterator for Values<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.entries.next().map(|a| a.1)
+    }
+}
+
+// the entries are inserted one by one, overwriting the existing ones
+impl<T: TrieData> Extend<(Vec<u8>, T)> for Trie<T> {
+    fn extend<I: IntoIterator<Item=(Vec<u8>, T)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(value, &key);
+        }
+    }
+}
+
+impl<T: TrieData> From<ContiguousTrie<T>> for Trie<T> {
+    fn from(trie: ContiguousTrie<T>) -> Self {
+        let mut result = Trie::new();
+        result.extend(trie.iter());
+        result
+    }
+}
+
+impl<T: TrieData> From<Trie<T>> for ContiguousTrie<T> {
+    // a lazily allocated trie of KEY_GROUP bit segments, the last one shorter if the key length
+    // is not a multiple of KEY_GROUP, every key of the trie must have the same length
+    fn from(trie: Trie<T>) -> Self {
+        let entries: Vec<(Vec<u8>, T)> = trie.iter().collect();
+        let key_length = entries.first().map(|a| a.0.len()).unwrap_or(KEY_LEN);
+        assert!(key_length > 0 && entries.iter().all(|a| a.0.len() == key_length), "keys of different lengths");
+        let mut segments = vec![KEY_GROUP; key_length / KEY_GROUP];
+        if key_length % KEY_GROUP != 0 {
+            segments.push(key_length % KEY_GROUP);
+        }
+        let mut result = ContiguousTrie::with_segments(&segments, Allocation::Lazy);
+        result.extend(entries);
+        result
+    }
+}
+
+// write every byte as its 8 binary digits, one nibble per node, the high nibble first. The digits
+// of a nibble go least significant first since compute_index reads them that way, so the child
+// index of a node is the nibble value and the children indices run in byte order
+fn byte_digits(bytes: &[u8]) -> Vec<u8> {
+    let mut digits = Vec::with_capacity(bytes.len() * 8);
+    for &byte in bytes {
+        for &i in [4, 5, 6, 7, 0, 1, 2, 3].iter() {
+            digits.push(if (byte >> i) & 1 == 1 { b'1' } else { b'0' });
+        }
+    }
+    digits
+}
+
+// the inverse of byte_digits
+fn digit_bytes(digits: &[u8]) -> Vec<u8> {
+    digits.chunks(8)
+        .map(|chunk| {
+            let nibble = |half: &[u8]| half.iter().rev().fold(0u8, |a, &digit| (a << 1) | (digit - b'0'));
+            (nibble(&chunk[..4]) << 4) | nibble(&chunk[4..])
+        })
+        .collect()
+}
+
+/// A Trie keyed by arbitrary byte strings such as &str, &[u8] or UUIDs instead of binary digit
+/// strings. Every byte is consumed nibble by nibble, one node level per nibble, which makes it a
+/// radix tree usable as a string dictionary. Its entries iterate in the byte order of their keys.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ByteTrie<T: TrieData> {
+    trie: Trie<T>,
+}
+
+impl<T: TrieData> ByteTrie<T> {
+    pub fn new() -> Self {
+        ByteTrie { trie: Trie::new() }
+    }
+
+    // the number of values stored
+    pub fn depth(&self) -> u32 {
+        self.trie.depth()
+    }
+
+    // return the number of values added, 0 when an existing value is overwritten
+    pub fn insert<K: AsRef<[u8]>>(&mut self, value: T, key: K) -> u32 {
+        self.trie.insert(value, &byte_digits(key.as_ref()))
+    }
+
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<T> {
+        self.trie.get(&byte_digits(key.as_ref()))
+    }
+
+    pub fn contain<K: AsRef<[u8]>>(&self, key: K) -> bool {
+        self.trie.contain(&byte_digits(key.as_ref()))
+    }
+
+    pub fn remove<K: AsRef<[u8]>>(&mut self, key: K) -> Option<T> {
+        self.trie.remove(&byte_digits(key.as_ref()))
+    }
+
+    // return the value stored by the longest prefix of key, with the length of that prefix in bytes
+    pub fn longest_prefix_match<K: AsRef<[u8]>>(&self, key: K) -> Option<(usize, T)> {
+        self.trie.longest_prefix_match(&byte_digits(key.as_ref())).map(|(length, data)| (length / 8, data))
+    }
+
+    // return the values stored by every prefix of key, shortest prefix first, lengths in bytes
+    pub fn all_prefix_matches<K: AsRef<[u8]>>(&self, key: K) -> Vec<(usize, T)> {
+        self.trie.all_prefix_matches(&byte_digits(key.as_ref())).into_iter()
+            .map(|(length, data)| (length / 8, data))
+            .collect()
+    }
+
+    // return all the entries with their byte keys, in byte order
+    pub fn iter(&self) -> ::std::vec::IntoIter<(Vec<u8>, T)> {
+        self.trie.iter()
+            .map(|(digits, data)| (digit_bytes(&digits), data))
+            .collect::<Vec<(Vec<u8>, T)>>()
+            .into_iter()
+    }
+
+    // the full byte keys starting with prefix in byte order, for instances for type-ahead search over
+    // identifiers, limit keeps the first ones
+    pub fn keys_with_prefix<'a, K: AsRef<[u8]>>(&'a self, prefix: K, limit: Option<usize>) -> ::std::iter::Map<Keys<'a, T>, fn(Vec<u8>) -> Vec<u8>> {
+        fn to_bytes(digits: Vec<u8>) -> Vec<u8> {
+            digit_bytes(&digits)
         }
+        self.trie.keys_with_prefix(&byte_digits(prefix.as_ref()), limit).map(to_bytes as fn(Vec<u8>) -> Vec<u8>)
+    }
+
+    // the values whose byte keys start with prefix, in the byte order of their keys
+    pub fn values_with_prefix<'a, K: AsRef<[u8]>>(&'a self, prefix: K, limit: Option<usize>) -> Values<'a, T> {
+        self.trie.values_with_prefix(&byte_digits(prefix.as_ref()), limit)
     }
 }
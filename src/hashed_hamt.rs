@@ -0,0 +1,142 @@
+/// Hash trie front end for arbitrary Hash keys, with a HashMap like API
+///
+/// A key is hashed to a fixed width binary string which indexes the backend trie, Trie or
+/// ContiguousTrie or anything else implementing RemovableMap. The backend leaf only holds the index of a
+/// bucket in a side table, because the tries store Copy values, and the bucket holds the key/value
+/// pairs whose hashes are equal, so colliding keys are told apart by comparing the keys.
+///
+/// remove drops the trie entry of a bucket it leaves empty and puts the bucket on a free list,
+/// which insert takes the buckets of new hashes from before growing the table.
+
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::mem;
+
+use cchamt::{Allocation, ContiguousTrie};
+use hamt::Trie;
+use map::RemovableMap;
+use node::{BitKey, Hashed};
+
+/// Core Data structure
+#[derive(Debug)]
+pub struct HashedTrie<K, V, M = Trie<usize>> {
+    trie: M,
+    buckets: Vec<Vec<(K, V)>>,
+    free: Vec<usize>,   // the empty buckets no trie entry points to
+    width: usize,       // the number of hash bits indexing the trie
+    len: usize,
+}
+
+impl<K: Hash + Eq, V> HashedTrie<K, V, Trie<usize>> {
+    pub fn new() -> Self {
+        HashedTrie::with_backend(Trie::new(), 32)
+    }
+}
+
+impl<K: Hash + Eq, V> HashedTrie<K, V, ContiguousTrie<usize>> {
+    // index a lazily allocated ContiguousTrie with key_length bits of the hash
+    pub fn contiguous(key_length: usize, key_segment_size: usize) -> Self {
+        HashedTrie::with_backend(ContiguousTrie::with_allocation(key_length, key_segment_size, Allocation::Lazy), key_length)
+    }
+}
+
+impl<K: Hash + Eq, V, M: RemovableMap<[u8], usize>> HashedTrie<K, V, M> {
+    // the backend should be empty and accept keys of width binary digits
+    pub fn with_backend(trie: M, width: usize) -> Self {
+        HashedTrie {
+            trie,
+            buckets: vec![],
+            free: vec![],
+            width,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline(always)]
+    fn bucket<Q: ?Sized + Hash>(&self, key: &Q) -> Option<usize> {
+        self.trie.get(&Hashed(key).to_bits(self.width))
+    }
+
+    // insert the pair and return the value it replaced, like HashMap::insert
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let bits = Hashed(&key).to_bits(self.width);
+        let bucket = match self.trie.get(&bits) {
+            Some(bucket) => bucket,
+            None => {
+                let bucket = match self.free.pop() {
+                    Some(bucket) => bucket,
+                    None => {
+                        self.buckets.push(vec![]);
+                        self.buckets.len() - 1
+                    }
+                };
+                self.trie.insert(&bits, bucket);
+                bucket
+            }
+        };
+
+        let pairs = &mut self.buckets[bucket];
+        for pair in pairs.iter_mut() {
+            if pair.0 == key {
+                return Some(mem::replace(&mut pair.1, value));
+            }
+        }
+        pairs.push((key, value));
+        self.len += 1;
+        None
+    }
+
+    pub fn get<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<&V> where K: Borrow<Q> {
+        self.bucket(key).and_then(|bucket| {
+            self.buckets[bucket].iter()
+                .find(|pair| pair.0.borrow() == key)
+                .map(|pair| &pair.1)
+        })
+    }
+
+    pub fn get_mut<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<&mut V> where K: Borrow<Q> {
+        match self.bucket(key) {
+            Some(bucket) => self.buckets[bucket].iter_mut()
+                .find(|pair| pair.0.borrow() == key)
+                .map(|pair| &mut pair.1),
+            None => None,
+        }
+    }
+
+    pub fn contains_key<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> bool where K: Borrow<Q> {
+        self.get(key).is_some()
+    }
+
+    // remove the pair and return its value
+    pub fn remove<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<V> where K: Borrow<Q> {
+        let bits = Hashed(key).to_bits(self.width);
+        let bucket = match self.trie.get(&bits) {
+            Some(bucket) => bucket,
+            None => return None,
+        };
+        let position = match self.buckets[bucket].iter().position(|pair| pair.0.borrow() == key) {
+            Some(position) => position,
+            None => return None,
+        };
+        let (_, value) = self.buckets[bucket].swap_remove(position);
+        if self.buckets[bucket].is_empty() {
+            self.trie.remove(&bits);
+            self.free.push(bucket);
+        }
+        self.len -= 1;
+        Some(value)
+    }
+
+    // iterate the pairs in no particular order
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item=(&'a K, &'a V)> + 'a {
+        self.buckets.iter().flat_map(|pairs| pairs.iter().map(|pair| (&pair.0, &pair.1)))
+    }
+}
@@ -6,7 +6,6 @@
 #![feature(slice_get_slice)]
 #![feature(box_syntax)]
 #![feature(core_intrinsics)]
-#![feature(libc)]
 #![feature(integer_atomics)]
 #![feature(vec_resize_default)]
 
@@ -16,17 +15,39 @@ extern crate test;
 extern crate chashmap;
 extern crate rayon;
 
+mod node;
 mod hamt;
+mod persistent_hamt;
 mod cchamt;
 //mod bench;
 mod allocator;
 mod lockfree_cchamt;
 mod mutex_cchamt;
 mod rwlock_cchamt;
+mod element_cchamt;
+mod mutex_element_cchamt;
+mod rwlock_element_cchamt;
+mod seqlock_cchamt;
+mod atomic_cchamt;
+mod mmap_cchamt;
+mod map;
+mod hashed_hamt;
+mod set;
 
-pub use hamt::{Trie, TrieData, IndexStatus};
-pub use cchamt::ContiguousTrie;
+pub use node::{TrieData, TrieKey, BitKey, FromBits, Hashed, Iter, Change};
+pub use hamt::{Trie, ByteTrie, IndexStatus, PrefixEntries, Keys, Values};
+pub use persistent_hamt::PersistentTrie;
+pub use cchamt::{ContiguousTrie, Allocation, Layout};
 pub use allocator::Allocator;
 pub use lockfree_cchamt::LockfreeTrie;
 pub use mutex_cchamt::MutexContiguousTrie;
 pub use rwlock_cchamt::RwContiguousTrie;
+pub use element_cchamt::{ElementContiguousTrie, ElementLock};
+pub use mutex_element_cchamt::MutexElementContiguousTrie;
+pub use rwlock_element_cchamt::RwElementContiguousTrie;
+pub use seqlock_cchamt::{SeqlockContiguousTrie, WordData};
+pub use atomic_cchamt::AtomicContiguousTrie;
+pub use mmap_cchamt::{MappedContiguousTrie, PlainData};
+pub use map::{Map, RemovableMap, ConcurrentMap};
+pub use hashed_hamt::HashedTrie;
+pub use set::{TrieSet, ContiguousTrieSet, LockfreeTrieSet};
@@ -5,14 +5,7 @@ use std::option::Option;
 use std::ptr::null_mut;
 use allocator::Allocator;
 use std::thread;
-
-pub trait TrieData: Clone + Copy + Eq + PartialEq {}
-
-impl<T> TrieData for T where T: Clone + Copy + Eq + PartialEq {}
-
-pub trait TrieKey: Clone + Copy + Eq + PartialEq + Hash {}
-
-impl<T> TrieKey for T where T: Clone + Copy + Eq + PartialEq + Hash {}
+use node::{TrieData, TrieKey};
 
 type ANode<K, V> = Vec<AtomicPtr<Node<K, V>>>;
 
@@ -27,6 +20,7 @@ enum Node<K, V> {
     NoTxn,
     FSNode,
     FVNode,
+    Removed,    // the txn of an SNode being removed, its slot becomes empty
     FNode {
         frozen: AtomicPtr<Node<K, V>>
     },
@@ -99,6 +93,13 @@ pub struct LockfreeTrie<K: TrieKey, V: TrieData> {
     cache: AtomicPtr<CacheLevel<K, V>>,
 }
 
+// every node shared between threads is reached through an AtomicPtr and only changed by CAS, the
+// allocator hands every node a distinct slot by fetch_add and no node moves or is freed before
+// the trie is dropped, so the trie can be shared like the lock-free design intends
+unsafe impl<K: TrieKey + Send + Sync, V: TrieData + Send + Sync> Send for LockfreeTrie<K, V> {}
+
+unsafe impl<K: TrieKey + Send + Sync, V: TrieData + Send + Sync> Sync for LockfreeTrie<K, V> {}
+
 fn makeanode<K, V>(len: usize) -> ANode<K, V> {
     let mut a: ANode<K, V> = Vec::with_capacity(len);
 
@@ -120,6 +121,11 @@ impl<K: TrieKey, V: TrieData> LockfreeTrie<K, V> {
         }
     }
 
+    // the node a txn installs in the slot of its SNode, a removal empties the slot
+    fn _txn_target(txnptr: *mut Node<K, V>) -> *mut Node<K, V> {
+        if let Node::Removed = unsafe { &*txnptr } { null_mut() } else { txnptr }
+    }
+
     fn _freeze(mem: &Allocator<Node<K, V>>, nnode: &mut Node<K, V>) -> () {
         if let Node::ANode(ref cur) = nnode {
             let mut i = 0;
@@ -141,7 +147,7 @@ impl<K: TrieKey, V: TrieData> LockfreeTrie<K, V> {
                             i -= 1;
                         }
                     } else if let Node::FSNode = txnref {} else {
-                        node.compare_and_swap(nodeptr, txnptr, Ordering::Relaxed);
+                        node.compare_and_swap(nodeptr, LockfreeTrie::_txn_target(txnptr), Ordering::Relaxed);
                         i -= 1;
                     }
                 } else if let Node::ANode(ref an) = noderef {
@@ -338,7 +344,7 @@ impl<K: TrieKey, V: TrieData> LockfreeTrie<K, V> {
                 } else if let Node::FSNode = txnref {
                     false
                 } else {
-                    old.compare_and_swap(oldptr, txnptr, Ordering::Relaxed);
+                    old.compare_and_swap(oldptr, LockfreeTrie::_txn_target(txnptr), Ordering::Relaxed);
                     LockfreeTrie::_insert(mem, key, val, h, lev, cur, prev)
                 }
             } else {
@@ -353,11 +359,66 @@ impl<K: TrieKey, V: TrieData> LockfreeTrie<K, V> {
         }
     }
 
-    pub fn insert(&mut self, key: K, val: V) -> bool {
-        LockfreeTrie::_insert(&mut self.mem, key, val, hash(key), 0, unsafe { &mut *self.root.load(Ordering::Relaxed) }, None)
+    pub fn insert(&self, key: K, val: V) -> bool {
+        LockfreeTrie::_insert(&self.mem, key, val, hash(key), 0, unsafe { &mut *self.root.load(Ordering::Relaxed) }, None)
             || self.insert(key, val)
     }
 
+    // None when the walk met a frozen node and has to restart from the root
+    fn _remove(mem: &Allocator<Node<K, V>>, key: &K, h: u64, lev: u8, cur: &Node<K, V>) -> Option<Option<V>> {
+        if let Node::ANode(ref cur2) = cur {
+            let pos = (h >> lev) as usize & (cur2.len() - 1);
+            let old = &cur2[pos];
+            let oldptr = old.load(Ordering::Relaxed);
+
+            if oldptr.is_null() {
+                return Some(None);
+            }
+            match unsafe { &*oldptr } {
+                Node::ANode(_) => LockfreeTrie::_remove(mem, key, h, lev + 4, unsafe { &*oldptr }),
+                Node::SNode { key: ref _key, ref val, ref txn, .. } => {
+                    let txnptr = txn.load(Ordering::Relaxed);
+                    match unsafe { &*txnptr } {
+                        Node::NoTxn => {
+                            if *_key != *key {
+                                Some(None)
+                            } else if txn.compare_and_swap(txnptr, mem.alloc(Node::Removed), Ordering::Relaxed) == txnptr {
+                                old.compare_and_swap(oldptr, null_mut(), Ordering::Relaxed);
+                                Some(Some(*val))
+                            } else {
+                                LockfreeTrie::_remove(mem, key, h, lev, cur)
+                            }
+                        }
+                        Node::FSNode => None,
+                        _ => {
+                            old.compare_and_swap(oldptr, LockfreeTrie::_txn_target(txnptr), Ordering::Relaxed);
+                            LockfreeTrie::_remove(mem, key, h, lev, cur)
+                        }
+                    }
+                }
+                Node::ENode { .. } => {
+                    LockfreeTrie::_complete_expansion(mem, unsafe { &mut *oldptr });
+                    None
+                }
+                _ => None,
+            }
+        } else {
+            // this has never happened once, but just to be sure...
+            panic!("CORRUPTION: cur is not an ANode")
+        }
+    }
+
+    // remove the key and return its value, the emptied slot is reused by later inserts
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let h = hash(key);
+        loop {
+            let root = unsafe { &*self.root.load(Ordering::Relaxed) };
+            if let Some(result) = LockfreeTrie::_remove(&self.mem, key, h, 0, root) {
+                return result;
+            }
+        }
+    }
+
     fn _inhabit<'a>(&'a self,
                     cache: Option<&'a CacheLevel<K, V>>,
                     nv: *mut Node<K, V>,
@@ -453,6 +514,40 @@ impl<K: TrieKey, V: TrieData> LockfreeTrie<K, V> {
         }
     }
 
+    fn _collect(node: &Node<K, V>, entries: &mut Vec<(K, V)>) -> () {
+        if let Node::ANode(ref an) = node {
+            for v in an {
+                let vptr = v.load(Ordering::Relaxed);
+
+                if !vptr.is_null() {
+                    let vref = unsafe { &*vptr };
+
+                    if let Node::SNode { key, val, txn, .. } = vref {
+                        if let Node::Removed = unsafe { &*txn.load(Ordering::Relaxed) } {} else {
+                            entries.push((*key, *val));
+                        }
+                    } else if let Node::ANode(_) = vref {
+                        LockfreeTrie::_collect(vref, entries);
+                    } else if let Node::ENode { narrow, .. } = vref {
+                        LockfreeTrie::_collect(unsafe { &*narrow.load(Ordering::Relaxed) }, entries);
+                    } else if let Node::FNode { frozen } = vref {
+                        LockfreeTrie::_collect(unsafe { &*frozen.load(Ordering::Relaxed) }, entries);
+                    }
+                }
+            }
+        }
+    }
+
+    // a snapshot of the entries in no particular order, it is only consistent while no writer runs
+    pub(crate) fn entries(&self) -> Vec<(K, V)> {
+        let mut entries = Vec::new();
+
+        let root = unsafe { &*self.root.load(Ordering::Relaxed) };
+        LockfreeTrie::_collect(root, &mut entries);
+
+        entries
+    }
+
     fn _sample_snodes_levels(&self) -> Vec<i32> {
         let mut hist = Vec::new();
 
@@ -478,7 +573,7 @@ impl<K: TrieKey, V: TrieData> LockfreeTrie<K, V> {
                 None
             } else if let Node::ANode(ref an) = oldref {
                 self._lookup(key, h, lev + 4, oldref, cache, cache_lev)
-            } else if let Node::SNode { key: _key, val, .. } = oldref {
+            } else if let Node::SNode { key: _key, val, txn, .. } = oldref {
                 if let Some(clev) = cache_lev {
                     if !(lev >= clev || lev <= clev + 4) {
                         self._record_miss();
@@ -487,7 +582,8 @@ impl<K: TrieKey, V: TrieData> LockfreeTrie<K, V> {
                         self._inhabit(cache, oldptr, h, lev + 4);
                     }
                 }
-                if *_key == *key {
+                let removed = if let Node::Removed = unsafe { &*txn.load(Ordering::Relaxed) } { true } else { false };
+                if *_key == *key && !removed {
                     Some(val)
                 } else {
                     None
@@ -0,0 +1,205 @@
+/// Common interfaces over the trie implementations
+///
+/// `Map` is for a trie owned by one thread and mutated through `&mut self`, `ConcurrentMap` is
+/// for a trie shared between threads and mutated through `&self`. Both take the key first and
+/// insert overwrites an existing entry, so code written against them runs on any implementation.
+/// Every ConcurrentMap is also a Map, which lets single threaded code take the concurrent tries too.
+/// `RemovableMap` adds remove for the single threaded tries that support it.
+
+use hamt::{ByteTrie, Trie};
+use persistent_hamt::PersistentTrie;
+use cchamt::ContiguousTrie;
+use lockfree_cchamt::LockfreeTrie;
+use node::{TrieData, TrieKey};
+use mutex_cchamt::MutexContiguousTrie;
+use rwlock_cchamt::RwContiguousTrie;
+use mutex_element_cchamt::MutexElementContiguousTrie;
+use rwlock_element_cchamt::RwElementContiguousTrie;
+use seqlock_cchamt::{SeqlockContiguousTrie, WordData};
+use atomic_cchamt::AtomicContiguousTrie;
+
+pub trait Map<K: ?Sized, V> {
+    fn insert(&mut self, key: &K, value: V);
+
+    fn get(&self, key: &K) -> Option<V>;
+
+    fn contains(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+}
+
+pub trait RemovableMap<K: ?Sized, V>: Map<K, V> {
+    // remove the entry and return its value
+    fn remove(&mut self, key: &K) -> Option<V>;
+}
+
+pub trait ConcurrentMap<K: ?Sized, V> {
+    fn insert(&self, key: &K, value: V);
+
+    fn get(&self, key: &K) -> Option<V>;
+
+    fn contains(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+}
+
+impl<T: TrieData> Map<[u8], T> for Trie<T> {
+    fn insert(&mut self, key: &[u8], value: T) {
+        self.insert(value, key);
+    }
+
+    fn get(&self, key: &[u8]) -> Option<T> {
+        self.get(key)
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        self.contain(key)
+    }
+}
+
+impl<T: TrieData> Map<[u8], T> for ByteTrie<T> {
+    fn insert(&mut self, key: &[u8], value: T) {
+        self.insert(value, key);
+    }
+
+    fn get(&self, key: &[u8]) -> Option<T> {
+        self.get(key)
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        self.contain(key)
+    }
+}
+
+// insert replaces self with the new version, the older versions held elsewhere are unchanged
+impl<T: TrieData> Map<[u8], T> for PersistentTrie<T> {
+    fn insert(&mut self, key: &[u8], value: T) {
+        *self = PersistentTrie::insert(self, value, key);
+    }
+
+    fn get(&self, key: &[u8]) -> Option<T> {
+        self.get(key)
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        self.contain(key)
+    }
+}
+
+impl<T: TrieData> Map<[u8], T> for ContiguousTrie<T> {
+    fn insert(&mut self, key: &[u8], value: T) {
+        self.insert(value, key);
+    }
+
+    fn get(&self, key: &[u8]) -> Option<T> {
+        self.get(key)
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        self.contain(key)
+    }
+}
+
+impl<T: TrieData> RemovableMap<[u8], T> for Trie<T> {
+    fn remove(&mut self, key: &[u8]) -> Option<T> {
+        self.remove(key)
+    }
+}
+
+impl<T: TrieData> RemovableMap<[u8], T> for ByteTrie<T> {
+    fn remove(&mut self, key: &[u8]) -> Option<T> {
+        self.remove(key)
+    }
+}
+
+impl<T: TrieData> RemovableMap<[u8], T> for PersistentTrie<T> {
+    fn remove(&mut self, key: &[u8]) -> Option<T> {
+        let old = self.get(key);
+        if old.is_some() {
+            *self = PersistentTrie::remove(self, key);
+        }
+        old
+    }
+}
+
+impl<T: TrieData> RemovableMap<[u8], T> for ContiguousTrie<T> {
+    fn remove(&mut self, key: &[u8]) -> Option<T> {
+        self.remove(key)
+    }
+}
+
+// the contiguous tries that all share the insert(value, key) / get / contain methods
+macro_rules! concurrent_contiguous_map {
+    ($trie:ident, $data:ident) => {
+        impl<T: $data> ConcurrentMap<[u8], T> for $trie<T> {
+            fn insert(&self, key: &[u8], value: T) {
+                self.insert(value, key);
+            }
+
+            fn get(&self, key: &[u8]) -> Option<T> {
+                self.get(key)
+            }
+
+            fn contains(&self, key: &[u8]) -> bool {
+                self.contain(key)
+            }
+        }
+    };
+}
+
+concurrent_contiguous_map!(MutexContiguousTrie, TrieData);
+concurrent_contiguous_map!(RwContiguousTrie, TrieData);
+concurrent_contiguous_map!(MutexElementContiguousTrie, TrieData);
+concurrent_contiguous_map!(RwElementContiguousTrie, TrieData);
+concurrent_contiguous_map!(SeqlockContiguousTrie, WordData);
+
+impl ConcurrentMap<[u8], usize> for AtomicContiguousTrie {
+    fn insert(&self, key: &[u8], value: usize) {
+        self.insert(value, key);
+    }
+
+    fn get(&self, key: &[u8]) -> Option<usize> {
+        self.get(key)
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        self.contain(key)
+    }
+}
+
+impl<K: TrieKey, V: TrieData> ConcurrentMap<K, V> for LockfreeTrie<K, V> {
+    fn insert(&self, key: &K, value: V) {
+        LockfreeTrie::insert(self, *key, value);
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        self.lookup(key).cloned()
+    }
+}
+
+// a blanket impl over ConcurrentMap would overlap the Map impls above, so list the types instead
+macro_rules! map_by_concurrent_map {
+    ($trie:ty, $key:ty, $value:ty, $($bounds:tt)*) => {
+        impl<$($bounds)*> Map<$key, $value> for $trie {
+            fn insert(&mut self, key: &$key, value: $value) {
+                ConcurrentMap::insert(self, key, value)
+            }
+
+            fn get(&self, key: &$key) -> Option<$value> {
+                ConcurrentMap::get(self, key)
+            }
+
+            fn contains(&self, key: &$key) -> bool {
+                ConcurrentMap::contains(self, key)
+            }
+        }
+    };
+}
+
+map_by_concurrent_map!(MutexContiguousTrie<T>, [u8], T, T: TrieData);
+map_by_concurrent_map!(RwContiguousTrie<T>, [u8], T, T: TrieData);
+map_by_concurrent_map!(MutexElementContiguousTrie<T>, [u8], T, T: TrieData);
+map_by_concurrent_map!(RwElementContiguousTrie<T>, [u8], T, T: TrieData);
+map_by_concurrent_map!(SeqlockContiguousTrie<T>, [u8], T, T: WordData);
+map_by_concurrent_map!(AtomicContiguousTrie, [u8], usize, );
+map_by_concurrent_map!(LockfreeTrie<K, V>, K, V, K: TrieKey, V: TrieData);
@@ -0,0 +1,296 @@
+/// Read-only ContiguousTrie served from a memory mapped image
+///
+/// The image written by `ContiguousTrie::write_to` is the `memory` vector laid out as fixed size
+/// records, so a lookup just follows children_offset through the mapping without deserializing.
+///
+/// Layout of the image, every field but the magic bytes is a native endian u64:
+///     magic "CCHAMT\0\0", version, value size, slot size, key length, number of levels,
+///     segment size of every level, number of slots, then the slot records
+/// Layout of a slot record:
+///     tag (u32), depth (u32), children_offset (u64), value bytes padded to 8 bytes
+///
+/// Values are written through PlainData, which only the integer types implement, so an image
+/// never holds padding or pointers and every value read back is valid. open only checks the
+/// header and refuses a corrupt or truncated one with an io::Error, so a large image starts
+/// without touching its slot pages. Lookups bound every index by the slot count and check the
+/// tag, and verify checks every record against the header when a full scan is wanted.
+
+extern crate libc;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::marker::PhantomData;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use node::{compute_index, walk_key, SubTrie, TrieData};
+
+const MAGIC: &[u8; 8] = b"CCHAMT\0\0";
+const VERSION: u64 = 1;
+
+const TAG_EMPTY: u32 = 0;
+const TAG_INTERIOR: u32 = 1;
+const TAG_LEAF: u32 = 2;
+
+/// A value that an image can store as bytes: it has no padding or pointers and every pattern of
+/// SIZE bytes is a valid value
+pub trait PlainData: TrieData {
+    const SIZE: usize;
+
+    // write the native endian bytes of the value to bytes, which is SIZE long
+    fn write_bytes(&self, bytes: &mut [u8]);
+
+    fn read_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! plain_data {
+    ($($int:ty: $size:expr),*) => {
+        $(
+            impl PlainData for $int {
+                const SIZE: usize = $size;
+
+                fn write_bytes(&self, bytes: &mut [u8]) {
+                    let native: [u8; $size] = unsafe { mem::transmute(*self) };
+                    bytes.copy_from_slice(&native);
+                }
+
+                fn read_bytes(bytes: &[u8]) -> Self {
+                    let mut native = [0u8; $size];
+                    native.copy_from_slice(bytes);
+                    unsafe { mem::transmute(native) }
+                }
+            }
+        )*
+    };
+}
+
+plain_data!(u8: 1, u16: 2, u32: 4, u64: 8, i8: 1, i16: 2, i32: 4, i64: 8);
+
+#[cfg(target_pointer_width = "64")]
+plain_data!(usize: 8, isize: 8);
+
+#[cfg(target_pointer_width = "32")]
+plain_data!(usize: 4, isize: 4);
+
+#[inline(always)]
+fn slot_size<T: PlainData>() -> usize {
+    16 + (T::SIZE + 7) / 8 * 8
+}
+
+fn write_u64<W: Write>(w: &mut W, v: u64) -> io::Result<()> {
+    let bytes: [u8; 8] = unsafe { mem::transmute(v) };
+    w.write_all(&bytes)
+}
+
+fn write_u32<W: Write>(w: &mut W, v: u32) -> io::Result<()> {
+    let bytes: [u8; 4] = unsafe { mem::transmute(v) };
+    w.write_all(&bytes)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub(crate) fn write_image<T: PlainData, P: AsRef<Path>>(path: P, key_length: usize, segments: &[usize],
+                                                         memory: &[Option<SubTrie<T>>]) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    let mut value = vec![0u8; slot_size::<T>() - 16];
+    let empty = vec![0u8; slot_size::<T>()];
+
+    w.write_all(MAGIC)?;
+    write_u64(&mut w, VERSION)?;
+    write_u64(&mut w, T::SIZE as u64)?;
+    write_u64(&mut w, slot_size::<T>() as u64)?;
+    write_u64(&mut w, key_length as u64)?;
+    write_u64(&mut w, segments.len() as u64)?;
+    for &s in segments {
+        write_u64(&mut w, s as u64)?;
+    }
+    write_u64(&mut w, memory.len() as u64)?;
+
+    for slot in memory {
+        match slot {
+            Some(a) => {
+                let tag = if a.children_offset.is_some() { TAG_INTERIOR } else { TAG_LEAF };
+                write_u32(&mut w, tag)?;
+                write_u32(&mut w, a.depth as u32)?;
+                write_u64(&mut w, a.children_offset.unwrap_or(0) as u64)?;
+                match a.data {
+                    Some(ref data) => data.write_bytes(&mut value[..T::SIZE]),
+                    None => value[..T::SIZE].copy_from_slice(&empty[..T::SIZE]),
+                }
+                w.write_all(&value)?;
+            }
+            None => w.write_all(&empty)?,    // all zero, the tag is TAG_EMPTY
+        }
+    }
+    w.flush()
+}
+
+/// Core Data structure
+#[derive(Debug)]
+pub struct MappedContiguousTrie<T: TrieData> {
+    base: *const u8,
+    length: usize,
+    slots: *const u8,
+    slot_count: usize,
+    key_length: usize,
+    segments: Vec<usize>,
+    _data: PhantomData<T>,
+}
+
+unsafe impl<T: TrieData + Send> Send for MappedContiguousTrie<T> {}
+
+unsafe impl<T: TrieData + Sync> Sync for MappedContiguousTrie<T> {}
+
+impl<T: PlainData> MappedContiguousTrie<T> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let length = file.metadata()?.len() as usize;
+        if length < 8 * 7 {
+            return Err(invalid_data("image is too short"));
+        }
+
+        let base = unsafe {
+            libc::mmap(ptr::null_mut(), length as libc::size_t, libc::PROT_READ, libc::MAP_SHARED, file.as_raw_fd(), 0)
+        };
+        if base == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        // the trie owns the mapping from here on, so an invalid header still unmaps it
+        let mut trie = MappedContiguousTrie {
+            base: base as *const u8,
+            length,
+            slots: ptr::null(),
+            slot_count: 0,
+            key_length: 0,
+            segments: vec![],
+            _data: PhantomData,
+        };
+
+        let header = |i: usize| unsafe { ptr::read_unaligned((base as *const u64).offset(i as isize)) };
+        if unsafe { slice::from_raw_parts(base as *const u8, 8) } != MAGIC {
+            return Err(invalid_data("not a ContiguousTrie image"));
+        }
+        if header(1) != VERSION {
+            return Err(invalid_data("unsupported ContiguousTrie image version"));
+        }
+        if header(2) != T::SIZE as u64 || header(3) != slot_size::<T>() as u64 {
+            return Err(invalid_data("image was written with a different value type"));
+        }
+        // bound levels by the length before reading the segments, so a huge count can not wrap
+        let levels = header(5) as usize;
+        let header_length = match levels.checked_add(7).and_then(|a| a.checked_mul(8)) {
+            Some(a) if levels > 0 && a <= length => a,
+            _ => return Err(invalid_data("image is too short")),
+        };
+        trie.key_length = header(4) as usize;
+        trie.segments = (0..levels).map(|i| header(6 + i) as usize).collect();
+        if trie.segments.iter().any(|&s| s == 0 || s >= 64) || trie.segments.iter().sum::<usize>() != trie.key_length {
+            return Err(invalid_data("image has invalid segments"));
+        }
+        trie.slot_count = header(6 + levels) as usize;
+        let slots_length = trie.slot_count.checked_mul(slot_size::<T>());
+        if slots_length.and_then(|a| a.checked_add(header_length)) != Some(length) {
+            return Err(invalid_data("image length does not match its slot count"));
+        }
+        trie.slots = unsafe { trie.base.offset(header_length as isize) };
+        Ok(trie)
+    }
+
+    // check every slot record against the header, this reads the whole image
+    pub fn verify(&self) -> io::Result<()> {
+        // an interior slot must sit above the leaf level, its children_offset may point past the
+        // last slot since the leaf blocks of an eager trie only grow as far as the last entry
+        let levels = self.segments.len();
+        for index in 0..self.slot_count {
+            let (tag, depth, _) = self.slot(index);
+            let valid = match tag {
+                TAG_EMPTY => true,
+                TAG_INTERIOR => depth + 1 < levels,
+                TAG_LEAF => depth < levels,
+                _ => false,
+            };
+            if !valid {
+                return Err(invalid_data("image has a corrupt slot record"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn key_length(&self) -> usize {
+        self.key_length
+    }
+
+    // number of slots in the image
+    pub fn memory_len(&self) -> usize {
+        self.slot_count
+    }
+
+    // return (tag, depth, children_offset) of a slot
+    #[inline(always)]
+    fn slot(&self, index: usize) -> (u32, usize, usize) {
+        unsafe {
+            let record = self.slots.offset((index * slot_size::<T>()) as isize);
+            (ptr::read_unaligned(record as *const u32),
+             ptr::read_unaligned(record.offset(4) as *const u32) as usize,
+             ptr::read_unaligned(record.offset(8) as *const u64) as usize)
+        }
+    }
+
+    #[inline(always)]
+    fn value(&self, index: usize) -> T {
+        T::read_bytes(unsafe { slice::from_raw_parts(self.slots.offset((index * slot_size::<T>() + 16) as isize), T::SIZE) })
+    }
+
+    // same walk as ContiguousTrie::key2index, over the mapped records, an interior record on the
+    // leaf level of a corrupt image ends the walk like a leaf
+    #[inline(always)]
+    fn key2index(&self, key: &[u8]) -> usize {
+        let levels = self.segments.len();
+        walk_key(key, compute_index(key, self.segments[0]), |depth| self.segments[depth], |index, depth| {
+            if depth + 1 >= levels || index >= self.slot_count {
+                return None;
+            }
+            match self.slot(index) {
+                (TAG_INTERIOR, _, children_offset) => Some(children_offset),
+                _ => None,
+            }
+        }).0
+    }
+
+    #[inline(always)]
+    pub fn contain(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    #[inline(always)]
+    pub fn get(&self, key: &[u8]) -> Option<T> {
+        let current_index = self.key2index(key);
+        if self.slot_count <= current_index || self.slot(current_index).0 != TAG_LEAF {
+            return None;
+        }
+        Some(self.value(current_index))
+    }
+
+    // return the depth of the entry stored by the key, the top level is depth 0
+    pub fn depth_of(&self, key: &[u8]) -> Option<usize> {
+        let current_index = self.key2index(key);
+        if self.slot_count <= current_index || self.slot(current_index).0 != TAG_LEAF {
+            return None;
+        }
+        Some(self.slot(current_index).1)
+    }
+}
+
+impl<T: TrieData> Drop for MappedContiguousTrie<T> {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.base as *mut libc::c_void, self.length as libc::size_t);
+        }
+    }
+}
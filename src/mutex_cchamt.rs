@@ -3,27 +3,9 @@
 use std::sync::{Arc, Mutex};
 use std::thread;
 
-pub trait TrieData: Clone + Copy + Eq + PartialEq {}
-
-impl<T> TrieData for T where T: Clone + Copy + Eq + PartialEq {}
-
-/// Private Functions for this module
-/// compute the depth in the trie using the array index of trie.memory
-// TODO bug here
-#[inline(always)]
-fn get_depth(key_group: usize, index: usize) -> usize {
-    let mut depth = 0;
-    let key_length = u32::pow(2, key_group as u32);
-    let mut multitude = key_length;
-    let mut compare = multitude;
-
-    while index >= compare as usize {
-        depth += 1;
-        multitude *= key_length;
-        compare += multitude;
-    }
-    depth
-}
+use cchamt::ContiguousTrie;
+use node::{compute_index, walk_key, SubTrie, TrieData};
+use rwlock_cchamt::RwContiguousTrie;
 
 /// Core Data structure
 #[derive(Debug)]
@@ -33,14 +15,6 @@ pub struct MutexContiguousTrie<T: TrieData> {
     key_segment_size: usize,
 }
 
-
-#[derive(Clone, Eq, PartialEq, Debug)]
-pub struct SubTrie<T: TrieData> {
-    pub data: Option<T>,
-    depth: usize,
-    children_offset: Option<usize>,    // the start position in allocator that place the array in hash trie
-}
-
 // Contiguous store all the nodes contiguous with the sequential order of key
 impl<T: TrieData> MutexContiguousTrie<T> {
     pub fn new(key_length: usize, key_segment_size: usize) -> Self {
@@ -61,14 +35,19 @@ impl<T: TrieData> MutexContiguousTrie<T> {
             memory = Arc::new(Mutex::new(Vec::with_capacity(nodes_length)));
 
             let mut this = memory.lock().unwrap();
-            for i in 0..nodes_length {
-                (*this).push(Some(SubTrie {
-                    data: None,
-//                    depth: get_depth(key_segment_size as usize, i),
-                    depth: 0,
-                    children_offset: Some((i + 1) * array_length as usize),
-                }));
-//                println!("co {} {}", i, (i + 1) * array_length as usize);
+            // nodes are pushed level by level, so the depth is the level being filled
+            let mut level_length = array_length;
+            for depth in 0..(key_length / key_segment_size - 1) {
+                for _ in 0..level_length {
+                    let i = (*this).len();
+                    (*this).push(Some(SubTrie {
+                        data: None,
+                        depth,
+                        children_offset: Some((i + 1) * array_length as usize),
+                    }));
+//                    println!("co {} {}", i, (i + 1) * array_length as usize);
+                }
+                level_length *= array_length;
             }
         }
 
@@ -79,68 +58,88 @@ impl<T: TrieData> MutexContiguousTrie<T> {
         }
     }
 
-    // return the index in the first <= 4 bits
-// for instances: 0000 0000 -> 0
-    #[inline(always)]
-    fn compute_index(&self, key: &[u8]) -> usize {
-        let mut id = 0;
-        let length = if key.len() > self.key_segment_size { self.key_segment_size } else { key.len() };
-        for i in 0..length {
-            let temp = key[i] as usize - '0' as usize;
-            id += temp << (length - i - 1);
-        }
-        return id as usize;
-    }
-
-    // key should be 1-1 mapping to self memory array
+    // key should be 1-1 mapping to self memory array, the caller holds the lock
     #[inline(always)]
-    fn key2index(&self, key: &[u8]) -> usize {
-        let mut current_index = self.compute_index(key);
-        let mut key_start = 0;
-        let mut this = self.memory.lock().unwrap();
-        while (*this).len() > current_index && (*this)[current_index].is_some() {
-//            println!("comp_index {} ci {} {}", self.compute_index(&key[key_start..]), current_index, self.memory.len());
-            match &(*this)[current_index] {
-                Some(a) => {
-                    match a.children_offset {
-                        Some(b) => {
-                            key_start += self.key_segment_size;
-                            current_index = b + self.compute_index(&key[key_start..]);
-                        }
-                        None => break,
-                    }
-                }
-                None => break,
-            }
-        }
-        current_index
+    fn key2index(&self, this: &Vec<Option<SubTrie<T>>>, key: &[u8]) -> (usize, usize) {
+        walk_key(key, compute_index(key, self.key_segment_size), |_| self.key_segment_size,
+                 |index, _| this.get(index).and_then(|a| a.as_ref()).and_then(|a| a.children_offset))
     }
 
-    pub fn insert(&self, value: T, key: &[u8]) {
-        let current_index = self.key2index(key);
-        let mut this = self.memory.lock().unwrap();
-//        println!("debug {} {}", current_index, self.memory.len());
+    // store a leaf at the index found by key2index and return the value it replaced,
+    // the caller holds the lock
+    fn write_leaf(this: &mut Vec<Option<SubTrie<T>>>, current_index: usize, depth: usize, value: T) -> Option<T> {
+//        println!("debug {} {}", current_index, this.len());
         if current_index >= (*this).len() {
             let push_amount = current_index - (*this).len() + 1;
             for _ in 0..push_amount {
                 (*this).push(None);
             }
         }
-        if (*this)[current_index].is_some() {
-            assert!(false);
-        }
+        let old = (*this)[current_index].as_ref().and_then(|a| a.data);
         (*this)[current_index] = Some(SubTrie {
             data: Some(value),
-//            depth: get_depth(self.key_length, current_index),
-            depth: 0,
+            depth,
             children_offset: None,
         });
+        old
+    }
+
+    // read the leaf at the index found by key2index, the caller holds the lock
+    fn read_leaf(this: &Vec<Option<SubTrie<T>>>, current_index: usize) -> Option<T> {
+        if (*this).len() <= current_index {
+            return None;
+        }
+        match &(*this)[current_index] {
+            Some(a) => {
+                a.data
+            }
+            None => None,
+        }
+    }
+
+    // insert the entry and return the value it replaced
+    pub fn insert(&self, value: T, key: &[u8]) -> Option<T> {
+        let mut this = self.memory.lock().unwrap();
+        let (current_index, depth) = self.key2index(&*this, key);
+        MutexContiguousTrie::write_leaf(&mut *this, current_index, depth, value)
+    }
+
+    // insert all the entries under a single lock acquisition, in the order of their slots,
+    // a key repeated in the batch ends up with its last value
+    pub fn insert_batch(&self, entries: &[(T, &[u8])]) {
+        let mut this = self.memory.lock().unwrap();
+        let mut slots: Vec<(usize, usize, T)> = entries.iter()
+            .map(|&(value, key)| {
+                let (current_index, depth) = self.key2index(&*this, key);
+                (current_index, depth, value)
+            })
+            .collect();
+        // the sort is stable, so repeated keys are still written in batch order
+        slots.sort_by_key(|a| a.0);
+        for (current_index, depth, value) in slots {
+            MutexContiguousTrie::write_leaf(&mut *this, current_index, depth, value);
+        }
+    }
+
+    // look all the keys up under a single lock acquisition, reading the slots in memory order
+    pub fn get_batch(&self, keys: &[&[u8]]) -> Vec<Option<T>> {
+        let this = self.memory.lock().unwrap();
+        let mut slots: Vec<(usize, usize)> = keys.iter()
+            .enumerate()
+            .map(|(position, key)| (self.key2index(&*this, key).0, position))
+            .collect();
+        slots.sort();
+        let mut result = vec![None; keys.len()];
+        for (current_index, position) in slots {
+            result[position] = MutexContiguousTrie::read_leaf(&*this, current_index);
+        }
+        result
     }
 
     #[inline(always)]
     pub fn contain(&self, key: &[u8]) -> bool {
-        let current_index = self.key2index(key);
-        let mut this = self.memory.lock().unwrap();
+        let this = self.memory.lock().unwrap();
+        let (current_index, _) = self.key2index(&*this, key);
         if (*this).len() <= current_index {
             return false;
         }
@@ -152,22 +151,78 @@ impl<T: TrieData> MutexContiguousTrie<T> {
         }
     }
 
+    // return the depth of the entry stored by the key, the top level is depth 0
+    pub fn depth_of(&self, key: &[u8]) -> Option<usize> {
+        let this = self.memory.lock().unwrap();
+        let (current_index, _) = self.key2index(&*this, key);
+        match (*this).get(current_index) {
+            Some(Some(a)) if a.data.is_some() => Some(a.depth),
+            _ => None,
+        }
+    }
+
+    // return the deepest depth among the stored entries, which bounds the hops of a lookup
+    pub fn max_depth(&self) -> usize {
+        let this = self.memory.lock().unwrap();
+        (*this).iter()
+            .filter_map(|a| a.as_ref())
+            .filter(|a| a.data.is_some())
+            .map(|a| a.depth)
+            .max()
+            .unwrap_or(0)
+    }
+
     #[inline(always)]
     pub fn get(&self, key: &[u8]) -> Option<T> {
-        let current_index = self.key2index(key);
-        let mut this = self.memory.lock().unwrap();
-        if (*this).len() <= current_index {
-            return None;
-        }
-        match &(*this)[current_index] {
-            Some(a) => {
-                a.data
+        let this = self.memory.lock().unwrap();
+        let (current_index, _) = self.key2index(&*this, key);
+        MutexContiguousTrie::read_leaf(&*this, current_index)
+    }
+
+    // take memory out of the lock, it has the breadth-first layout of ContiguousTrie
+    pub(crate) fn into_memory(self) -> (Vec<Option<SubTrie<T>>>, usize, usize) {
+        let memory = match Arc::try_unwrap(self.memory) {
+            Ok(memory) => memory.into_inner().unwrap(),
+            Err(memory) => memory.lock().unwrap().clone(),
+        };
+        (memory, self.key_length, self.key_segment_size)
+    }
+}
+
+// the entries are inserted under a single lock acquisition, overwriting the existing ones
+impl<T: TrieData> Extend<(Vec<u8>, T)> for MutexContiguousTrie<T> {
+    fn extend<I: IntoIterator<Item=(Vec<u8>, T)>>(&mut self, iter: I) {
+        let entries: Vec<(Vec<u8>, T)> = iter.into_iter().collect();
+        let batch: Vec<(T, &[u8])> = entries.iter().map(|&(ref key, value)| (value, &key[..])).collect();
+        self.insert_batch(&batch);
+    }
+}
+
+impl<T: TrieData> From<ContiguousTrie<T>> for MutexContiguousTrie<T> {
+    // moves memory when the trie has the breadth-first layout of uniform segments, otherwise
+    // re-inserts the entries into a trie with the largest segment size that fits every level
+    fn from(trie: ContiguousTrie<T>) -> Self {
+        match trie.into_breadth_first() {
+            Ok((memory, key_length, key_segment_size)) => MutexContiguousTrie {
+                memory: Arc::new(Mutex::new(memory)),
+                key_length,
+                key_segment_size,
+            },
+            Err(trie) => {
+                let mut result = MutexContiguousTrie::new(trie.key_length(), trie.common_segment_size());
+                result.extend(trie.iter());
+                result
             }
-            None => None,
         }
     }
 }
 
+impl<T: TrieData> From<RwContiguousTrie<T>> for MutexContiguousTrie<T> {
+    fn from(trie: RwContiguousTrie<T>) -> Self {
+        MutexContiguousTrie::from(ContiguousTrie::from(trie))
+    }
+}
+
 // TODO should change this to key_length+2, which is {:0key_length+2b}
 #[macro_export]
 macro_rules! binary_format {
@@ -0,0 +1,24 @@
+/// Concurrent Cache Conscious Hash Trie using a Mutex Lock per element
+///
+/// Every leaf slot carries its own Mutex, see `ElementContiguousTrie` for the layout.
+
+use std::sync::Mutex;
+
+use element_cchamt::{ElementContiguousTrie, ElementLock};
+
+impl<X> ElementLock<X> for Mutex<X> {
+    fn new(value: X) -> Self {
+        Mutex::new(value)
+    }
+
+    fn read<R, F: FnOnce(&X) -> R>(&self, f: F) -> R {
+        f(&*self.lock().unwrap())
+    }
+
+    fn write<R, F: FnOnce(&mut X) -> R>(&self, f: F) -> R {
+        f(&mut *self.lock().unwrap())
+    }
+}
+
+/// Core Data structure
+pub type MutexElementContiguousTrie<T> = ElementContiguousTrie<T, Mutex<Option<T>>>;
@@ -0,0 +1,222 @@
+/// Node and key abstractions shared by every trie implementation
+///
+/// All the tries index by keys written as binary digit strings such as "0101", most significant
+/// bit first. BitKey turns binary strings, integers and hashed values into that form, and
+/// FromBits turns the strings handed out by iteration back into integers, so an entry can move
+/// from one implementation to another.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+pub trait TrieData: Clone + Copy + Eq + PartialEq {}
+
+impl<T> TrieData for T where T: Clone + Copy + Eq + PartialEq {}
+
+/// The key of LockfreeTrie, which hashes its keys itself
+pub trait TrieKey: Clone + Copy + Eq + PartialEq + Hash {}
+
+impl<T> TrieKey for T where T: Clone + Copy + Eq + PartialEq + Hash {}
+
+/// A slot in the memory of the contiguous tries
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SubTrie<T: TrieData> {
+    pub data: Option<T>,
+    pub(crate) depth: usize,
+    pub(crate) children_offset: Option<usize>,    // the start position in allocator that place the array in hash trie
+}
+
+// return the index in the first <= segment_size bits of the key, most significant bit first
+// for instances: 0000 0000 -> 0
+#[inline(always)]
+pub(crate) fn compute_index(key: &[u8], segment_size: usize) -> usize {
+    let mut id = 0;
+    let length = if key.len() > segment_size { segment_size } else { key.len() };
+    for i in 0..length {
+        let temp = key[i] as usize - '0' as usize;
+        id += temp << (length - i - 1);
+    }
+    id
+}
+
+// walk a key down the memory of a contiguous trie from the slot first of its top level.
+// segment(depth) is the segment size of a level and children(index, depth) the children_offset
+// of an interior slot, the walk returns (index, depth) of the first slot that is not one
+#[inline(always)]
+pub(crate) fn walk_key<S, C>(key: &[u8], first: usize, segment: S, mut children: C) -> (usize, usize)
+    where S: Fn(usize) -> usize, C: FnMut(usize, usize) -> Option<usize> {
+    let mut current_index = first;
+    let mut key_start = 0;
+    let mut depth = 0;
+    while let Some(offset) = children(current_index, depth) {
+        key_start += segment(depth);
+        depth += 1;
+        current_index = offset.saturating_add(compute_index(&key[key_start..], segment(depth)));
+    }
+    (current_index, depth)
+}
+
+/// What happened to one key between two versions of a trie, as returned by diff
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Change<T> {
+    Added(Vec<u8>, T),
+    Removed(Vec<u8>, T),
+    /// the key with its old and its new value
+    Changed(Vec<u8>, T, T),
+}
+
+impl<T: TrieData> Change<T> {
+    pub fn key(&self) -> &[u8] {
+        match self {
+            Change::Added(key, _) | Change::Removed(key, _) | Change::Changed(key, _, _) => key,
+        }
+    }
+
+    // the change of a key holding old before and new after, None when it did not change
+    pub(crate) fn between(key: &[u8], old: Option<T>, new: Option<T>) -> Option<Change<T>> {
+        match (old, new) {
+            (Some(a), Some(b)) if a != b => Some(Change::Changed(key.to_vec(), a, b)),
+            (Some(a), None) => Some(Change::Removed(key.to_vec(), a)),
+            (None, Some(b)) => Some(Change::Added(key.to_vec(), b)),
+            _ => None,
+        }
+    }
+}
+
+/// A key that can be written as a binary digit string
+pub trait BitKey {
+    // return the key as exactly `length` binary digits, most significant bit first
+    fn to_bits(&self, length: usize) -> Vec<u8>;
+}
+
+/// A key that can be read back from a binary digit string
+pub trait FromBits: Sized {
+    fn from_bits(bits: &[u8]) -> Self;
+}
+
+/// A key indexed by its hash, for keys that are not integers or binary strings
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub struct Hashed<K>(pub K);
+
+// left pad a binary digit string with zeros up to length
+fn pad_bits(bits: &[u8], length: usize) -> Vec<u8> {
+    assert!(bits.len() <= length, "key is longer than {} bits", length);
+    let mut result = vec![b'0'; length - bits.len()];
+    result.extend_from_slice(bits);
+    result
+}
+
+// a binary digit string is taken as it is, a shorter one is left padded with zeros
+impl BitKey for [u8] {
+    fn to_bits(&self, length: usize) -> Vec<u8> {
+        assert!(self.iter().all(|&b| b == b'0' || b == b'1'), "key is not a binary digit string");
+        pad_bits(self, length)
+    }
+}
+
+impl BitKey for Vec<u8> {
+    fn to_bits(&self, length: usize) -> Vec<u8> {
+        self[..].to_bits(length)
+    }
+}
+
+macro_rules! integer_bit_key {
+    ($($int:ty),*) => {
+        $(
+            impl BitKey for $int {
+                fn to_bits(&self, length: usize) -> Vec<u8> {
+                    pad_bits(format!("{:b}", self).as_bytes(), length)
+                }
+            }
+
+            impl FromBits for $int {
+                fn from_bits(bits: &[u8]) -> Self {
+                    let mut id: $int = 0;
+                    for &b in bits {
+                        id = id.checked_mul(2).expect("key does not fit in the integer type") + (b - b'0') as $int;
+                    }
+                    id
+                }
+            }
+        )*
+    };
+}
+
+integer_bit_key!(u8, u16, u32, u64, usize);
+
+// the hash is truncated to its lowest `length` bits, so distinct keys may collide
+impl<K: Hash> BitKey for Hashed<K> {
+    fn to_bits(&self, length: usize) -> Vec<u8> {
+        let mut hasher = DefaultHasher::new();
+        self.0.hash(&mut hasher);
+        let hash = hasher.finish();
+        let hash = if length < 64 { hash & ((1 << length) - 1) } else { hash };
+        hash.to_bits(length)
+    }
+}
+
+/// Iterator over the entries of a contiguous memory vector in key order, the keys are binary
+/// digit strings. The root block holds root_width slots whose top level key index starts at
+/// root_first, which lets a stripe of RwContiguousTrie be walked like a whole trie.
+pub struct Iter<'a, T: TrieData + 'a> {
+    memory: &'a [Option<SubTrie<T>>],
+    segments: Vec<usize>,
+    root_width: usize,
+    root_first: usize,
+    stack: Vec<(usize, usize, usize, usize)>,    // (block offset, depth, key start, next slot) of the open blocks
+    key: Vec<u8>,
+}
+
+impl<'a, T: TrieData> Iter<'a, T> {
+    pub(crate) fn new(memory: &'a [Option<SubTrie<T>>], segments: &[usize], root_width: usize, root_first: usize) -> Self {
+        Iter {
+            memory,
+            segments: segments.to_vec(),
+            root_width,
+            root_first,
+            stack: vec![(0, 0, 0, 0)],
+            key: vec![],
+        }
+    }
+}
+
+impl<'a, T: TrieData> Iterator for Iter<'a, T> {
+    type Item = (Vec<u8>, T);
+
+    fn next(&mut self) -> Option<(Vec<u8>, T)> {
+        loop {
+            let (offset, depth, key_start, slot) = match self.stack.last_mut() {
+                Some(top) => {
+                    let current = *top;
+                    top.3 += 1;
+                    current
+                }
+                None => return None,
+            };
+            let segment_size = self.segments[depth];
+            let (width, first) = if depth == 0 {
+                (self.root_width, self.root_first)
+            } else {
+                (usize::pow(2, segment_size as u32), 0)
+            };
+            if slot == width {
+                self.stack.pop();
+                continue;
+            }
+
+            self.key.truncate(key_start);
+            let index = first + slot;
+            for i in (0..segment_size).rev() {
+                self.key.push(if (index >> i) & 1 == 1 { b'1' } else { b'0' });
+            }
+            match self.memory.get(offset + slot) {
+                Some(Some(a)) => match a.children_offset {
+                    Some(b) => self.stack.push((b, depth + 1, key_start + segment_size, 0)),
+                    None => if let Some(data) = a.data {
+                        return Some((self.key.clone(), data));
+                    },
+                },
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,221 @@
+/// Persistent hash trie, the immutable counterpart of `Trie`
+///
+/// insert and remove leave the trie untouched and return a new version: only the nodes on the
+/// path to the key are copied, every other subtree is shared with the old version through an Arc.
+/// Versions are cheap to keep and to hand to readers on other threads, and two versions can be
+/// compared subtree by subtree with ptr_eq. Keys are indexed exactly like in `Trie`.
+
+use std::sync::Arc;
+
+use hamt::{compute_index, group_length, push_index_digits, CHILDREN};
+use node::{Change, TrieData};
+
+#[derive(Eq, PartialEq, Debug)]
+struct Node<T: TrieData> {
+    data: Option<T>,
+    depth: u32,                     // the number of values stored in this subtree
+    bitmap: u32,                    // bit i is set when child i exists
+    children: Vec<Arc<Node<T>>>,    // the existing children in the order of their index
+}
+
+/// Core Data structure
+#[derive(Eq, PartialEq, Debug)]
+pub struct PersistentTrie<T: TrieData> {
+    root: Arc<Node<T>>,
+}
+
+// cloning a version only clones the root pointer
+impl<T: TrieData> Clone for PersistentTrie<T> {
+    fn clone(&self) -> Self {
+        PersistentTrie { root: self.root.clone() }
+    }
+}
+
+impl<T: TrieData> Node<T> {
+    fn empty() -> Self {
+        Node { data: None, depth: 0, bitmap: 0, children: vec![] }
+    }
+
+    // the position of child index in the dense children array
+    #[inline(always)]
+    fn child_position(&self, index: usize) -> usize {
+        (self.bitmap & ((1 << index) - 1)).count_ones() as usize
+    }
+
+    #[inline(always)]
+    fn child(&self, index: usize) -> Option<&Arc<Node<T>>> {
+        if self.bitmap & (1 << index) == 0 {
+            None
+        } else {
+            Some(&self.children[self.child_position(index)])
+        }
+    }
+
+    // return the node with child index replaced, a child without any value is left out
+    fn with_child(&self, index: usize, child: Arc<Node<T>>) -> Node<T> {
+        let position = self.child_position(index);
+        let old_depth = self.child(index).map(|a| a.depth).unwrap_or(0);
+        let mut bitmap = self.bitmap;
+        let mut children = self.children.clone();
+        match (self.bitmap & (1 << index) != 0, child.depth != 0) {
+            (true, true) => children[position] = child.clone(),
+            (true, false) => {
+                children.remove(position);
+                bitmap &= !(1 << index);
+            }
+            (false, true) => {
+                children.insert(position, child.clone());
+                bitmap |= 1 << index;
+            }
+            (false, false) => {}
+        }
+        Node {
+            data: self.data,
+            depth: self.depth - old_depth + child.depth,
+            bitmap,
+            children,
+        }
+    }
+
+    fn insert(this: &Arc<Node<T>>, value: T, key: &[u8]) -> Arc<Node<T>> {
+        if key.len() == 0 {
+            return Arc::new(Node {
+                data: Some(value),
+                depth: if this.data.is_none() { this.depth + 1 } else { this.depth },
+                bitmap: this.bitmap,
+                children: this.children.clone(),
+            });
+        }
+
+        let index = compute_index(key);
+        let child = match this.child(index) {
+            Some(child) => Node::insert(child, value, &key[group_length(key)..]),
+            None => Node::insert(&Arc::new(Node::empty()), value, &key[group_length(key)..]),
+        };
+        Arc::new(this.with_child(index, child))
+    }
+
+    // return this itself when the key is absent, so nothing is copied
+    fn remove(this: &Arc<Node<T>>, key: &[u8]) -> Arc<Node<T>> {
+        if key.len() == 0 {
+            if this.data.is_none() {
+                return this.clone();
+            }
+            return Arc::new(Node {
+                data: None,
+                depth: this.depth - 1,
+                bitmap: this.bitmap,
+                children: this.children.clone(),
+            });
+        }
+
+        let index = compute_index(key);
+        match this.child(index) {
+            Some(child) => {
+                let new_child = Node::remove(child, &key[group_length(key)..]);
+                if Arc::ptr_eq(child, &new_child) {
+                    this.clone()
+                } else {
+                    Arc::new(this.with_child(index, new_child))
+                }
+            }
+            None => this.clone(),
+        }
+    }
+
+    fn get_node<'a>(this: &'a Arc<Node<T>>, key: &[u8]) -> Option<&'a Arc<Node<T>>> {
+        if key.len() == 0 {
+            Some(this)
+        } else {
+            this.child(compute_index(key)).and_then(|a| Node::get_node(a, &key[group_length(key)..]))
+        }
+    }
+
+    // a subtree shared by both versions holds no change, so it is skipped without being walked
+    fn diff(old: Option<&Arc<Node<T>>>, new: Option<&Arc<Node<T>>>, key: &mut Vec<u8>, changes: &mut Vec<Change<T>>) {
+        if let (Some(a), Some(b)) = (old, new) {
+            if Arc::ptr_eq(a, b) {
+                return;
+            }
+        }
+        changes.extend(Change::between(key, old.and_then(|a| a.data), new.and_then(|b| b.data)));
+        for index in 0..CHILDREN {
+            let a = old.and_then(|a| a.child(index));
+            let b = new.and_then(|b| b.child(index));
+            if a.is_some() || b.is_some() {
+                let length = key.len();
+                push_index_digits(index, key);
+                Node::diff(a, b, key, changes);
+                key.truncate(length);
+            }
+        }
+    }
+
+    fn collect_entries(&self, key: &mut Vec<u8>, entries: &mut Vec<(Vec<u8>, T)>) {
+        if let Some(data) = self.data {
+            entries.push((key.clone(), data));
+        }
+        for index in 0..CHILDREN {
+            if let Some(child) = self.child(index) {
+                let length = key.len();
+                push_index_digits(index, key);
+                child.collect_entries(key, entries);
+                key.truncate(length);
+            }
+        }
+    }
+}
+
+impl<T: TrieData> PersistentTrie<T> {
+    pub fn new() -> Self {
+        PersistentTrie { root: Arc::new(Node::empty()) }
+    }
+
+    // the number of values stored in this version
+    pub fn depth(&self) -> u32 {
+        self.root.depth
+    }
+
+    // return a new version with the value inserted by the key, overwriting an existing value
+    pub fn insert(&self, value: T, key: &[u8]) -> PersistentTrie<T> {
+        PersistentTrie { root: Node::insert(&self.root, value, key) }
+    }
+
+    // return a new version without the key, the subtrees left without any value are dropped
+    pub fn remove(&self, key: &[u8]) -> PersistentTrie<T> {
+        PersistentTrie { root: Node::remove(&self.root, key) }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<T> {
+        Node::get_node(&self.root, key).and_then(|a| a.data)
+    }
+
+    pub fn contain(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    // return the subtrie under a key prefix, it shares its nodes with self
+    pub fn get_sub_trie(&self, key: &[u8]) -> Option<PersistentTrie<T>> {
+        Node::get_node(&self.root, key).map(|a| PersistentTrie { root: a.clone() })
+    }
+
+    // true when both versions are the very same nodes, which implies equal contents
+    pub fn ptr_eq(&self, other: &PersistentTrie<T>) -> bool {
+        Arc::ptr_eq(&self.root, &other.root)
+    }
+
+    // the changes that turn the old version into the new one, in the order of the children
+    // indices. Only the paths where the versions stopped sharing nodes are walked
+    pub fn diff(old: &PersistentTrie<T>, new: &PersistentTrie<T>) -> Vec<Change<T>> {
+        let mut changes = vec![];
+        Node::diff(Some(&old.root), Some(&new.root), &mut vec![], &mut changes);
+        changes
+    }
+
+    // return all the entries with their binary keys, in the order of the children indices
+    pub fn iter(&self) -> ::std::vec::IntoIter<(Vec<u8>, T)> {
+        let mut entries = vec![];
+        self.root.collect_entries(&mut vec![], &mut entries);
+        entries.into_iter()
+    }
+}
@@ -4,157 +4,207 @@ use std::sync::{Arc, RwLock};
 use std::thread;
 use std::time::SystemTime;
 
-pub trait TrieData: Clone + Copy + Eq + PartialEq {}
-
-impl<T> TrieData for T where T: Clone + Copy + Eq + PartialEq {}
-
-/// Private Functions for this module
-/// compute the depth in the trie using the array index of trie.memory
-// TODO bug here
-#[inline(always)]
-fn get_depth(key_group: usize, index: usize) -> usize {
-    let mut depth = 0;
-    let key_length = u32::pow(2, key_group as u32);
-    let mut multitude = key_length;
-    let mut compare = multitude;
-
-    while index >= compare as usize {
-        depth += 1;
-        multitude *= key_length;
-        compare += multitude;
-    }
-    depth
-}
+use cchamt::ContiguousTrie;
+use mutex_cchamt::MutexContiguousTrie;
+use node::{compute_index, walk_key, Iter, SubTrie, TrieData};
 
 /// Core Data structure
+/// memory is split into stripes selected by the top key bits, stripe s holds the subtrees of the
+/// top level slots [s * array_length / stripes, (s + 1) * array_length / stripes) in its own vector
+/// behind its own lock, so writers to different parts of the key space do not contend
 #[derive(Debug)]
 pub struct RwContiguousTrie<T: TrieData> {
-    memory: RwLock<Vec<Option<SubTrie<T>>>>,
+    memory: Vec<RwLock<Vec<Option<SubTrie<T>>>>>,
     key_length: usize,
     key_segment_size: usize,
-}
-
-
-#[derive(Clone, Eq, PartialEq, Debug)]
-pub struct SubTrie<T: TrieData> {
-    pub data: Option<T>,
-    depth: usize,
-    children_offset: Option<usize>,    // the start position in allocator that place the array in hash trie
+    stripe_width: usize,    // top level slots per stripe
 }
 
 // Contiguous store all the nodes contiguous with the sequential order of key
 impl<T: TrieData> RwContiguousTrie<T> {
     pub fn new(key_length: usize, key_segment_size: usize) -> Self {
+        RwContiguousTrie::with_stripes(key_length, key_segment_size, 1)
+    }
+
+    // stripes should be a power of two no larger than 2^key_segment_size
+    pub fn with_stripes(key_length: usize, key_segment_size: usize, stripes: usize) -> Self {
         assert_eq!(key_length % key_segment_size, 0);
+        let array_length = usize::pow(2, key_segment_size as u32);
+        assert!(stripes.is_power_of_two() && stripes <= array_length);
+        let stripe_width = array_length / stripes;
 
-        let memory: RwLock<Vec<Option<SubTrie<T>>>>;
+        let mut memory = Vec::with_capacity(stripes);
         // init with all nodes that is not leaf
-        // length = summation of KEY_LEN^1 to KEY_LEN^(KEY_LEN/KEY_GROUP-1)
-        {
+        // length = stripe width * summation of KEY_LEN^0 to KEY_LEN^(KEY_LEN/KEY_GROUP-2)
+        for _ in 0..stripes {
             let mut nodes_length = 0;
-            let array_length = usize::pow(2, key_segment_size as u32);
-            let mut multitude = array_length;
+            let mut multitude = stripe_width;
             for _ in 0..(key_length / key_segment_size - 1) {
                 nodes_length += multitude;
                 multitude *= array_length;
             }
 //            println!("nl {}", nodes_length);
-            memory = RwLock::new(Vec::with_capacity(nodes_length));
-
-            let mut this = memory.write().unwrap();
-            for i in 0..nodes_length {
-                (*this).push(Some(SubTrie {
-                    data: None,
-//                    depth: get_depth(key_segment_size as usize, i),
-                    depth: 0,
-                    children_offset: Some((i + 1) * array_length as usize),
-                }));
-//                println!("co {} {}", i, (i + 1) * array_length as usize);
+            let mut this = Vec::with_capacity(nodes_length);
+
+            // nodes are pushed level by level, so the depth is the level being filled
+            let mut level_length = stripe_width;
+            for depth in 0..(key_length / key_segment_size - 1) {
+                for _ in 0..level_length {
+                    let i = this.len();
+                    this.push(Some(SubTrie {
+                        data: None,
+                        depth,
+                        children_offset: Some(stripe_width + i * array_length as usize),
+                    }));
+//                    println!("co {} {}", i, stripe_width + i * array_length as usize);
+                }
+                level_length *= array_length;
             }
+            memory.push(RwLock::new(this));
         }
 
         RwContiguousTrie {
             memory,
             key_length,
             key_segment_size,
+            stripe_width,
         }
     }
 
-    // return the index in the first <= 4 bits
-// for instances: 0000 0000 -> 0
+    pub fn stripes(&self) -> usize {
+        self.memory.len()
+    }
+
+    // key should be 1-1 mapping to a stripe and the index of the top level slot in that stripe
     #[inline(always)]
-    fn compute_index(&self, key: &[u8]) -> usize {
-        let mut id = 0;
-        let length = if key.len() > self.key_segment_size { self.key_segment_size } else { key.len() };
-        for i in 0..length {
-            let temp = key[i] as usize - '0' as usize;
-            id += temp << (length - i - 1);
-        }
-        return id as usize;
+    fn key2stripe(&self, key: &[u8]) -> (usize, usize) {
+        let top_index = compute_index(key, self.key_segment_size);
+        (top_index / self.stripe_width, top_index % self.stripe_width)
     }
 
-    // key should be 1-1 mapping to self memory array
+    // key should be 1-1 mapping to the memory array of its stripe, the caller holds the stripe lock
     #[inline(always)]
-    fn key2index(&self, key: &[u8]) -> usize {
-        let mut current_index = self.compute_index(key);
-        let mut key_start = 0;
-        let this = self.memory.read().unwrap();
-        while (*this).len() > current_index && (*this)[current_index].is_some() {
-//            println!("comp_index {} ci {} {}", self.compute_index(&key[key_start..]), current_index, self.memory.len());
-            match &(*this)[current_index] {
-                Some(a) => {
-                    match a.children_offset {
-                        Some(b) => {
-                            key_start += self.key_segment_size;
-                            current_index = b + self.compute_index(&key[key_start..]);
-                        }
-                        None => break,
-                    }
-                }
-                None => break,
+    fn key2index(&self, this: &Vec<Option<SubTrie<T>>>, top_index: usize, key: &[u8]) -> (usize, usize) {
+        walk_key(key, top_index, |_| self.key_segment_size,
+                 |index, _| this.get(index).and_then(|a| a.as_ref()).and_then(|a| a.children_offset))
+    }
+
+    // store a leaf at the index found by key2index and return the value it replaced,
+    // the caller holds the write lock of the stripe
+    fn write_leaf(this: &mut Vec<Option<SubTrie<T>>>, current_index: usize, depth: usize, value: T) -> Option<T> {
+//        println!("debug {} {}", current_index, this.len());
+        if current_index >= (*this).len() {
+            let push_amount = current_index - (*this).len() + 1;
+            for _ in 0..push_amount {
+                (*this).push(None);
             }
         }
-        current_index
+
+        let old = (*this)[current_index].as_ref().and_then(|a| a.data);
+        (*this)[current_index] = Some(SubTrie {
+            data: Some(value),
+            depth,
+            children_offset: None,
+        });
+        old
     }
 
-    pub fn insert(&self, value: T, key: &[u8]) {
-        let current_index = self.key2index(key);
-        let mut length = 0;
-        {
-            let this = self.memory.read().unwrap();
-            length = (*this).len();
+    // read the leaf at the index found by key2index, the caller holds a lock of the stripe
+    fn read_leaf(this: &Vec<Option<SubTrie<T>>>, current_index: usize) -> Option<T> {
+        if (*this).len() <= current_index {
+            return None;
         }
-//        println!("debug {} {}", current_index, self.memory.len());
-        if current_index >= length {
-            let push_amount = current_index - length + 1;
-            {
-                let mut this = self.memory.write().unwrap();
-                for _ in 0..push_amount {
-                    (*this).push(None);
-                }
+        match &(*this)[current_index] {
+            Some(a) => {
+                a.data
             }
+            None => None,
         }
+    }
 
-        {
-            let this = self.memory.read().unwrap();
-            if (*this)[current_index].is_some() {
-                assert!(false);
+    // insert the entry and return the value it replaced
+    // the walk, the growth and the write all happen under one write lock of the stripe, so
+    // concurrent inserts of the same key are serialized and the later one overwrites
+    pub fn insert(&self, value: T, key: &[u8]) -> Option<T> {
+        let (stripe, top_index) = self.key2stripe(key);
+        let mut this = self.memory[stripe].write().unwrap();
+        let (current_index, depth) = self.key2index(&*this, top_index, key);
+        RwContiguousTrie::write_leaf(&mut *this, current_index, depth, value)
+    }
+
+    // insert all the entries taking the write lock of each stripe once, in the order of their
+    // slots, a key repeated in the batch ends up with its last value
+    pub fn insert_batch(&self, entries: &[(T, &[u8])]) {
+        let mut by_stripe: Vec<(usize, usize, usize)> = entries.iter()
+            .enumerate()
+            .map(|(position, &(_, key))| {
+                let (stripe, top_index) = self.key2stripe(key);
+                (stripe, top_index, position)
+            })
+            .collect();
+        by_stripe.sort_by_key(|a| a.0);
+
+        let mut start = 0;
+        while start < by_stripe.len() {
+            let stripe = by_stripe[start].0;
+            let mut end = start;
+            while end < by_stripe.len() && by_stripe[end].0 == stripe {
+                end += 1;
+            }
+
+            let mut this = self.memory[stripe].write().unwrap();
+            let mut slots: Vec<(usize, usize, usize)> = by_stripe[start..end].iter()
+                .map(|&(_, top_index, position)| {
+                    let (current_index, depth) = self.key2index(&*this, top_index, entries[position].1);
+                    (current_index, depth, position)
+                })
+                .collect();
+            slots.sort();
+            for (current_index, depth, position) in slots {
+                RwContiguousTrie::write_leaf(&mut *this, current_index, depth, entries[position].0);
             }
+            start = end;
         }
+    }
 
-        let mut this = self.memory.write().unwrap();
-        (*this)[current_index] = Some(SubTrie {
-            data: Some(value),
-//            depth: get_depth(self.key_length, current_index),
-            depth: 0,
-            children_offset: None,
-        });
+    // look all the keys up taking the read lock of each stripe once, reading the slots in memory order
+    pub fn get_batch(&self, keys: &[&[u8]]) -> Vec<Option<T>> {
+        let mut by_stripe: Vec<(usize, usize, usize)> = keys.iter()
+            .enumerate()
+            .map(|(position, key)| {
+                let (stripe, top_index) = self.key2stripe(key);
+                (stripe, top_index, position)
+            })
+            .collect();
+        by_stripe.sort_by_key(|a| a.0);
+
+        let mut result = vec![None; keys.len()];
+        let mut start = 0;
+        while start < by_stripe.len() {
+            let stripe = by_stripe[start].0;
+            let mut end = start;
+            while end < by_stripe.len() && by_stripe[end].0 == stripe {
+                end += 1;
+            }
+
+            let this = self.memory[stripe].read().unwrap();
+            let mut slots: Vec<(usize, usize)> = by_stripe[start..end].iter()
+                .map(|&(_, top_index, position)| (self.key2index(&*this, top_index, keys[position]).0, position))
+                .collect();
+            slots.sort();
+            for (current_index, position) in slots {
+                result[position] = RwContiguousTrie::read_leaf(&*this, current_index);
+            }
+            start = end;
+        }
+        result
     }
 
     #[inline(always)]
     pub fn contain(&self, key: &[u8]) -> bool {
-        let current_index = self.key2index(key);
-        let mut this = self.memory.read().unwrap();
+        let (stripe, top_index) = self.key2stripe(key);
+        let this = self.memory[stripe].read().unwrap();
+        let (current_index, _) = self.key2index(&*this, top_index, key);
         if (*this).len() <= current_index {
             return false;
         }
@@ -166,22 +216,103 @@ impl<T: TrieData> RwContiguousTrie<T> {
         }
     }
 
+    // return the depth of the entry stored by the key, the top level is depth 0
+    pub fn depth_of(&self, key: &[u8]) -> Option<usize> {
+        let (stripe, top_index) = self.key2stripe(key);
+        let this = self.memory[stripe].read().unwrap();
+        let (current_index, _) = self.key2index(&*this, top_index, key);
+        match (*this).get(current_index) {
+            Some(Some(a)) if a.data.is_some() => Some(a.depth),
+            _ => None,
+        }
+    }
+
+    // return the deepest depth among the stored entries, which bounds the hops of a lookup
+    pub fn max_depth(&self) -> usize {
+        self.memory.iter()
+            .map(|stripe| {
+                let this = stripe.read().unwrap();
+                (*this).iter()
+                    .filter_map(|a| a.as_ref())
+                    .filter(|a| a.data.is_some())
+                    .map(|a| a.depth)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
     #[inline(always)]
     pub fn get(&self, key: &[u8]) -> Option<T> {
-        let current_index = self.key2index(key);
-        let mut this = self.memory.read().unwrap();
-        if (*this).len() <= current_index {
-            return None;
+        let (stripe, top_index) = self.key2stripe(key);
+        let this = self.memory[stripe].read().unwrap();
+        let (current_index, _) = self.key2index(&*this, top_index, key);
+        RwContiguousTrie::read_leaf(&*this, current_index)
+    }
+
+    pub fn key_length(&self) -> usize {
+        self.key_length
+    }
+
+    pub(crate) fn key_segment_size(&self) -> usize {
+        self.key_segment_size
+    }
+
+    // collect the entries of a stripe in key order, under its read lock
+    pub(crate) fn stripe_entries(&self, stripe: usize) -> Vec<(Vec<u8>, T)> {
+        let this = self.memory[stripe].read().unwrap();
+        let segments = vec![self.key_segment_size; self.key_length / self.key_segment_size];
+        Iter::new(&*this, &segments, self.stripe_width, stripe * self.stripe_width).collect()
+    }
+
+    // take memory out of the lock if there is a single stripe, which then has the breadth-first
+    // layout of ContiguousTrie, otherwise give self back
+    pub(crate) fn into_memory(mut self) -> Result<(Vec<Option<SubTrie<T>>>, usize, usize), Self> {
+        if self.memory.len() != 1 {
+            return Err(self);
         }
-        match &(*this)[current_index] {
-            Some(a) => {
-                a.data
+        let memory = self.memory.pop().unwrap().into_inner().unwrap();
+        Ok((memory, self.key_length, self.key_segment_size))
+    }
+}
+
+// the entries are inserted taking each stripe lock once, overwriting the existing ones
+impl<T: TrieData> Extend<(Vec<u8>, T)> for RwContiguousTrie<T> {
+    fn extend<I: IntoIterator<Item=(Vec<u8>, T)>>(&mut self, iter: I) {
+        let entries: Vec<(Vec<u8>, T)> = iter.into_iter().collect();
+        let batch: Vec<(T, &[u8])> = entries.iter().map(|&(ref key, value)| (value, &key[..])).collect();
+        self.insert_batch(&batch);
+    }
+}
+
+impl<T: TrieData> From<ContiguousTrie<T>> for RwContiguousTrie<T> {
+    // moves memory into a single stripe when the trie has the breadth-first layout of uniform
+    // segments, otherwise re-inserts the entries into a trie with the largest segment size that
+    // fits every level
+    fn from(trie: ContiguousTrie<T>) -> Self {
+        match trie.into_breadth_first() {
+            Ok((memory, key_length, key_segment_size)) => RwContiguousTrie {
+                memory: vec![RwLock::new(memory)],
+                key_length,
+                key_segment_size,
+                stripe_width: usize::pow(2, key_segment_size as u32),
+            },
+            Err(trie) => {
+                let mut result = RwContiguousTrie::new(trie.key_length(), trie.common_segment_size());
+                result.extend(trie.iter());
+                result
             }
-            None => None,
         }
     }
 }
 
+impl<T: TrieData> From<MutexContiguousTrie<T>> for RwContiguousTrie<T> {
+    fn from(trie: MutexContiguousTrie<T>) -> Self {
+        RwContiguousTrie::from(ContiguousTrie::from(trie))
+    }
+}
+
 // TODO should change this to key_length+2, which is {:0key_length+2b}
 #[macro_export]
 macro_rules! binary_format {
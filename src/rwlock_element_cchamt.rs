@@ -0,0 +1,25 @@
+/// Concurrent Cache Conscious Hash Trie using a Read-Write Lock per element
+///
+/// Every leaf slot carries its own RwLock, so readers of the same slot do not block each other,
+/// see `ElementContiguousTrie` for the layout.
+
+use std::sync::RwLock;
+
+use element_cchamt::{ElementContiguousTrie, ElementLock};
+
+impl<X> ElementLock<X> for RwLock<X> {
+    fn new(value: X) -> Self {
+        RwLock::new(value)
+    }
+
+    fn read<R, F: FnOnce(&X) -> R>(&self, f: F) -> R {
+        f(&*RwLock::read(self).unwrap())
+    }
+
+    fn write<R, F: FnOnce(&mut X) -> R>(&self, f: F) -> R {
+        f(&mut *RwLock::write(self).unwrap())
+    }
+}
+
+/// Core Data structure
+pub type RwElementContiguousTrie<T> = ElementContiguousTrie<T, RwLock<Option<T>>>;
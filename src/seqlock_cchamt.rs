@@ -0,0 +1,275 @@
+/// Concurrent Cache Conscious Hash Trie using a Sequence Lock
+///
+/// Writers are serialized by a mutex and bump a sequence counter before and after every write,
+/// readers take no lock: they read the counter, walk the trie and retry if the counter was odd
+/// or changed meanwhile. Built for read mostly workloads.
+///
+/// Readers may race with a writer, so memory must never move under them: it is a directory of
+/// fixed size chunks that are allocated once and only freed when the trie is dropped. A slot is
+/// stored as atomic words, a tag, the depth, the children_offset and the words of the value
+/// written by WordData, so it has no padding and a reader decodes it straight from the words.
+/// Every pattern of words decodes to some slot, a torn one is discarded once the sequence shows
+/// a writer touched it. The interior slots are written before the trie is shared and never
+/// again, so the walk down to the leaf can use them right away.
+
+use std::marker::PhantomData;
+use std::ptr;
+use std::sync::Mutex;
+use std::sync::atomic::{self, AtomicPtr, AtomicUsize, Ordering};
+
+use node::{compute_index, walk_key, SubTrie, TrieData};
+
+const CHUNK_SIZE: usize = 4096;
+
+// the bits of the tag word of a slot, 0 is an empty slot
+const TAG_SLOT: usize = 1;
+const TAG_DATA: usize = 2;
+const TAG_CHILDREN: usize = 4;
+
+// the tag, the depth and the children_offset come before the words of the value
+const HEADER_WORDS: usize = 3;
+
+/// A value that a seqlock slot can store as plain words: every pattern of WORDS words is a valid
+/// value, so a torn read decodes to some value that the sequence then discards
+pub trait WordData: TrieData {
+    const WORDS: usize;
+
+    // hand f every word i of the value with i in 0..WORDS
+    fn write_words<F: FnMut(usize, usize)>(&self, f: &mut F);
+
+    // build a value from word(i) with i in 0..WORDS
+    fn read_words<F: FnMut(usize) -> usize>(word: &mut F) -> Self;
+}
+
+macro_rules! word_data {
+    ($($int:ty),*) => {
+        $(
+            impl WordData for $int {
+                const WORDS: usize = 1;
+
+                fn write_words<F: FnMut(usize, usize)>(&self, f: &mut F) {
+                    f(0, *self as usize);
+                }
+
+                fn read_words<F: FnMut(usize) -> usize>(word: &mut F) -> Self {
+                    word(0) as $int
+                }
+            }
+        )*
+    };
+}
+
+word_data!(u8, u16, u32, usize, i8, i16, i32, isize);
+
+#[cfg(target_pointer_width = "64")]
+word_data!(u64, i64);
+
+impl<A: WordData, B: WordData> WordData for (A, B) {
+    const WORDS: usize = A::WORDS + B::WORDS;
+
+    fn write_words<F: FnMut(usize, usize)>(&self, f: &mut F) {
+        self.0.write_words(f);
+        self.1.write_words(&mut |i, w| f(A::WORDS + i, w));
+    }
+
+    fn read_words<F: FnMut(usize) -> usize>(word: &mut F) -> Self {
+        let a = A::read_words(word);
+        (a, B::read_words(&mut |i| word(A::WORDS + i)))
+    }
+}
+
+// the number of words a slot takes in a chunk
+#[inline(always)]
+fn slot_words<T: WordData>() -> usize {
+    HEADER_WORDS + T::WORDS
+}
+
+/// Core Data structure
+#[derive(Debug)]
+pub struct SeqlockContiguousTrie<T: WordData> {
+    chunks: Vec<AtomicPtr<AtomicUsize>>,    // CHUNK_SIZE slots of slot_words words each
+    sequence: AtomicUsize,
+    writer: Mutex<()>,
+    capacity: usize,
+    key_length: usize,
+    key_segment_size: usize,
+    _data: PhantomData<T>,
+}
+
+// a chunk of empty slots, all their words are zero
+fn new_chunk<T: WordData>() -> *mut AtomicUsize {
+    let chunk: Box<[AtomicUsize]> = (0..CHUNK_SIZE * slot_words::<T>())
+        .map(|_| AtomicUsize::new(0))
+        .collect::<Vec<_>>()
+        .into_boxed_slice();
+    Box::into_raw(chunk) as *mut AtomicUsize
+}
+
+// Contiguous store all the nodes contiguous with the sequential order of key
+impl<T: WordData> SeqlockContiguousTrie<T> {
+    pub fn new(key_length: usize, key_segment_size: usize) -> Self {
+        assert_eq!(key_length % key_segment_size, 0);
+
+        // capacity = summation of KEY_LEN^1 to KEY_LEN^(KEY_LEN/KEY_GROUP), leaves included
+        let levels = key_length / key_segment_size;
+        let array_length = usize::pow(2, key_segment_size as u32);
+        let mut capacity = 0;
+        let mut multitude = array_length;
+        for _ in 0..levels {
+            capacity += multitude;
+            multitude *= array_length;
+        }
+        let mut chunks = Vec::with_capacity((capacity + CHUNK_SIZE - 1) / CHUNK_SIZE);
+        for _ in 0..chunks.capacity() {
+            chunks.push(AtomicPtr::new(ptr::null_mut()));
+        }
+
+        let trie = SeqlockContiguousTrie {
+            chunks,
+            sequence: AtomicUsize::new(0),
+            writer: Mutex::new(()),
+            capacity,
+            key_length,
+            key_segment_size,
+            _data: PhantomData,
+        };
+
+        // init with all nodes that is not leaf, level by level, so the depth is the level being filled
+        let mut i = 0;
+        let mut level_length = array_length;
+        for depth in 0..(levels - 1) {
+            for _ in 0..level_length {
+                trie.write_slot(i, Some(SubTrie {
+                    data: None,
+                    depth,
+                    children_offset: Some((i + 1) * array_length as usize),
+                }));
+                i += 1;
+            }
+            level_length *= array_length;
+        }
+        trie
+    }
+
+    // decode a slot word by word without any lock, an unallocated chunk reads as an empty slot.
+    // The slot may be torn by a concurrent writer, so it is only valid once the sequence says so
+    #[inline(always)]
+    fn read_slot(&self, index: usize) -> Option<SubTrie<T>> {
+        if index >= self.capacity {
+            return None;
+        }
+        let chunk = self.chunks[index / CHUNK_SIZE].load(Ordering::Acquire);
+        if chunk.is_null() {
+            return None;
+        }
+        let words = unsafe { chunk.offset(((index % CHUNK_SIZE) * slot_words::<T>()) as isize) };
+        let mut word = |i: usize| unsafe { (*words.offset(i as isize)).load(Ordering::Relaxed) };
+        let tag = word(0);
+        if tag & TAG_SLOT == 0 {
+            return None;
+        }
+        Some(SubTrie {
+            data: if tag & TAG_DATA != 0 { Some(T::read_words(&mut |i| word(HEADER_WORDS + i))) } else { None },
+            depth: word(1),
+            children_offset: if tag & TAG_CHILDREN != 0 { Some(word(2)) } else { None },
+        })
+    }
+
+    // only called by new or by the writer holding the writer lock, allocates the chunk on first touch
+    fn write_slot(&self, index: usize, slot: Option<SubTrie<T>>) {
+        let directory = &self.chunks[index / CHUNK_SIZE];
+        let mut chunk = directory.load(Ordering::Relaxed);
+        if chunk.is_null() {
+            chunk = new_chunk::<T>();
+            directory.store(chunk, Ordering::Release);
+        }
+        let words = unsafe { chunk.offset(((index % CHUNK_SIZE) * slot_words::<T>()) as isize) };
+        let mut store = |i: usize, w: usize| unsafe { (*words.offset(i as isize)).store(w, Ordering::Relaxed) };
+        match slot {
+            Some(a) => {
+                let mut tag = TAG_SLOT;
+                if let Some(data) = a.data {
+                    tag |= TAG_DATA;
+                    data.write_words(&mut |i, w| store(HEADER_WORDS + i, w));
+                }
+                if let Some(offset) = a.children_offset {
+                    tag |= TAG_CHILDREN;
+                    store(2, offset);
+                }
+                store(1, a.depth);
+                store(0, tag);
+            }
+            None => store(0, 0),
+        }
+    }
+
+    // the leaf slot of the key, the interior slots never change after new so they are read
+    // without the sequence
+    #[inline(always)]
+    fn key2index(&self, key: &[u8]) -> (usize, usize) {
+        let levels = self.key_length / self.key_segment_size;
+        walk_key(key, compute_index(key, self.key_segment_size), |_| self.key_segment_size, |index, depth| {
+            if depth + 1 < levels { self.read_slot(index).and_then(|a| a.children_offset) } else { None }
+        })
+    }
+
+    // run an optimistic read, retrying while a writer is active or has written meanwhile
+    #[inline(always)]
+    fn read<R, F: Fn() -> R>(&self, f: F) -> R {
+        loop {
+            let sequence = self.sequence.load(Ordering::Acquire);
+            if sequence & 1 == 1 {
+                atomic::spin_loop_hint();
+                continue;
+            }
+            let result = f();
+            atomic::fence(Ordering::Acquire);
+            if self.sequence.load(Ordering::Relaxed) == sequence {
+                return result;
+            }
+        }
+    }
+
+    // insert the entry and return the value it replaced
+    pub fn insert(&self, value: T, key: &[u8]) -> Option<T> {
+        let _writer = self.writer.lock().unwrap();
+        let (current_index, depth) = self.key2index(key);
+        assert!(current_index < self.capacity);
+
+        // no other writer runs, so the slot can not be torn
+        let old = self.read_slot(current_index);
+        self.sequence.fetch_add(1, Ordering::Relaxed);
+        atomic::fence(Ordering::Release);
+        self.write_slot(current_index, Some(SubTrie {
+            data: Some(value),
+            depth,
+            children_offset: None,
+        }));
+        self.sequence.fetch_add(1, Ordering::Release);
+        old.and_then(|a| a.data)
+    }
+
+    #[inline(always)]
+    pub fn contain(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    #[inline(always)]
+    pub fn get(&self, key: &[u8]) -> Option<T> {
+        let current_index = self.key2index(key).0;
+        self.read(|| self.read_slot(current_index)).and_then(|a| a.data)
+    }
+}
+
+impl<T: WordData> Drop for SeqlockContiguousTrie<T> {
+    fn drop(&mut self) {
+        for directory in &self.chunks {
+            let chunk = directory.load(Ordering::Relaxed);
+            if !chunk.is_null() {
+                unsafe {
+                    let _b = Box::from_raw(::std::slice::from_raw_parts_mut(chunk, CHUNK_SIZE * slot_words::<T>()) as *mut [AtomicUsize]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,203 @@
+/// Set types over the tries, for membership tests without values
+///
+/// TrieSet and ContiguousTrieSet store () in their trie, and their union, intersection and
+/// difference walk the two tries node by node: a subtree found on one side only is cloned or
+/// dropped whole without looking at its keys. The two contiguous tries must split keys the same
+/// way. LockfreeTrieSet is shared between threads, and since the shape of its nodes depends on
+/// the order of the inserts, its union, intersection and difference walk the entries of one set
+/// and look them up in the other instead.
+
+use cchamt::{Allocation, ContiguousTrie};
+use hamt::Trie;
+use lockfree_cchamt::LockfreeTrie;
+use node::TrieKey;
+
+// the value of a key in the union, the intersection and the difference of two sets
+fn union_value(_key: &[u8], a: Option<()>, b: Option<()>) -> Option<()> {
+    a.or(b)
+}
+
+fn intersection_value(_key: &[u8], a: Option<()>, b: Option<()>) -> Option<()> {
+    a.and(b)
+}
+
+fn difference_value(_key: &[u8], a: Option<()>, b: Option<()>) -> Option<()> {
+    if b.is_some() { None } else { a }
+}
+
+/// Set of binary keys of any length over Trie
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TrieSet {
+    trie: Trie<()>,
+}
+
+impl TrieSet {
+    pub fn new() -> Self {
+        TrieSet { trie: Trie::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.trie.depth() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // return true when the key was not in the set yet
+    pub fn insert(&mut self, key: &[u8]) -> bool {
+        self.trie.insert((), key) == 1
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.trie.contain(key)
+    }
+
+    // return true when the key was in the set
+    pub fn remove(&mut self, key: &[u8]) -> bool {
+        self.trie.remove(key).is_some()
+    }
+
+    // iterate the keys in the order of the children indices
+    pub fn iter(&self) -> impl Iterator<Item=Vec<u8>> {
+        self.trie.iter().map(|(key, _)| key)
+    }
+
+    pub fn union(&self, other: &TrieSet) -> TrieSet {
+        TrieSet { trie: self.trie.zip_with(&other.trie, true, true, &mut union_value) }
+    }
+
+    pub fn intersection(&self, other: &TrieSet) -> TrieSet {
+        TrieSet { trie: self.trie.zip_with(&other.trie, false, false, &mut intersection_value) }
+    }
+
+    pub fn difference(&self, other: &TrieSet) -> TrieSet {
+        TrieSet { trie: self.trie.zip_with(&other.trie, true, false, &mut difference_value) }
+    }
+}
+
+/// Set of binary keys of key_length digits over ContiguousTrie
+#[derive(Debug)]
+pub struct ContiguousTrieSet {
+    trie: ContiguousTrie<()>,
+    len: usize,
+}
+
+impl ContiguousTrieSet {
+    pub fn new(key_length: usize, key_segment_size: usize) -> Self {
+        ContiguousTrieSet::with_allocation(key_length, key_segment_size, Allocation::Eager)
+    }
+
+    pub fn with_allocation(key_length: usize, key_segment_size: usize, allocation: Allocation) -> Self {
+        ContiguousTrieSet {
+            trie: ContiguousTrie::with_allocation(key_length, key_segment_size, allocation),
+            len: 0,
+        }
+    }
+
+    // the set of the keys left in a combined trie
+    fn from_trie(trie: ContiguousTrie<()>) -> Self {
+        let len = trie.iter().count();
+        ContiguousTrieSet { trie, len }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // return true when the key was not in the set yet
+    pub fn insert(&mut self, key: &[u8]) -> bool {
+        let added = self.trie.insert((), key).is_none();
+        if added {
+            self.len += 1;
+        }
+        added
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.trie.get(key).is_some()
+    }
+
+    // return true when the key was in the set
+    pub fn remove(&mut self, key: &[u8]) -> bool {
+        let removed = self.trie.remove(key).is_some();
+        if removed {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    // iterate the keys in key order
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item=Vec<u8>> + 'a {
+        self.trie.iter().map(|(key, _)| key)
+    }
+
+    pub fn union(&self, other: &ContiguousTrieSet) -> ContiguousTrieSet {
+        ContiguousTrieSet::from_trie(self.trie.zip_with(&other.trie, true, true, &mut union_value))
+    }
+
+    pub fn intersection(&self, other: &ContiguousTrieSet) -> ContiguousTrieSet {
+        ContiguousTrieSet::from_trie(self.trie.zip_with(&other.trie, false, false, &mut intersection_value))
+    }
+
+    pub fn difference(&self, other: &ContiguousTrieSet) -> ContiguousTrieSet {
+        ContiguousTrieSet::from_trie(self.trie.zip_with(&other.trie, true, false, &mut difference_value))
+    }
+}
+
+/// Set shared between threads over LockfreeTrie
+pub struct LockfreeTrieSet<K: TrieKey> {
+    trie: LockfreeTrie<K, ()>,
+}
+
+impl<K: TrieKey> LockfreeTrieSet<K> {
+    pub fn new() -> Self {
+        LockfreeTrieSet { trie: LockfreeTrie::new() }
+    }
+
+    pub fn insert(&self, key: K) {
+        self.trie.insert(key, ());
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.trie.lookup(key).is_some()
+    }
+
+    // return true when the key was in the set
+    pub fn remove(&self, key: &K) -> bool {
+        self.trie.remove(key).is_some()
+    }
+
+    // iterate a snapshot of the keys in no particular order, only consistent while no writer runs
+    pub fn iter(&self) -> impl Iterator<Item=K> {
+        self.trie.entries().into_iter().map(|entry| entry.0)
+    }
+
+    pub fn union(&self, other: &LockfreeTrieSet<K>) -> LockfreeTrieSet<K> {
+        let result = LockfreeTrieSet::new();
+        for key in self.iter().chain(other.iter()) {
+            result.insert(key);
+        }
+        result
+    }
+
+    pub fn intersection(&self, other: &LockfreeTrieSet<K>) -> LockfreeTrieSet<K> {
+        let result = LockfreeTrieSet::new();
+        for key in self.iter().filter(|key| other.contains(key)) {
+            result.insert(key);
+        }
+        result
+    }
+
+    pub fn difference(&self, other: &LockfreeTrieSet<K>) -> LockfreeTrieSet<K> {
+        let result = LockfreeTrieSet::new();
+        for key in self.iter().filter(|key| !other.contains(key)) {
+            result.insert(key);
+        }
+        result
+    }
+}
@@ -0,0 +1,82 @@
+extern crate cchamt;
+
+use std::sync::Arc;
+use std::thread;
+use cchamt::AtomicContiguousTrie;
+
+const NTHREAD: usize = 4;
+
+#[test]
+fn test_atomic_insert_get_remove() {
+    let trie = AtomicContiguousTrie::new(32, 8);
+
+    for i in 0..100000 {
+        let arr = format!("{:032b}", i * 3).into_bytes();
+        assert_eq!(trie.insert(i, &arr), None);
+    }
+    for i in 0..100000 {
+        let arr = format!("{:032b}", i * 3).into_bytes();
+        assert_eq!(trie.get(&arr), Some(i));
+    }
+    assert!(!trie.contain(&format!("{:032b}", 1).into_bytes()));
+    assert!(!trie.contain(&format!("{:032b}", 1 << 31).into_bytes()));
+
+    let arr = format!("{:032b}", 300).into_bytes();
+    assert_eq!(trie.insert(7, &arr), Some(100));
+    assert_eq!(trie.remove(&arr), Some(7));
+    assert_eq!(trie.remove(&arr), None);
+    assert!(!trie.contain(&arr));
+}
+
+#[test]
+fn test_atomic_compare_exchange() {
+    let trie = AtomicContiguousTrie::new(16, 8);
+    let arr = format!("{:016b}", 42).into_bytes();
+
+    assert_eq!(trie.compare_exchange(&arr, Some(1), Some(2)), Err(None));
+    assert_eq!(trie.compare_exchange(&arr, None, Some(1)), Ok(None));
+    assert_eq!(trie.compare_exchange(&arr, None, Some(3)), Err(Some(1)));
+    assert_eq!(trie.compare_exchange(&arr, Some(1), None), Ok(Some(1)));
+    assert_eq!(trie.get(&arr), None);
+}
+
+#[test]
+fn test_atomic_concurrent_fetch_add() {
+    let trie = Arc::new(AtomicContiguousTrie::new(16, 4));
+    for i in 0..256 {
+        trie.insert(0, &format!("{:016b}", i).into_bytes());
+    }
+    assert_eq!(trie.fetch_add(&format!("{:016b}", 300).into_bytes(), 1), None);
+
+    let mut thread_handle = vec![];
+    for _ in 0..NTHREAD {
+        let thread_trie = trie.clone();
+        thread_handle.push(thread::spawn(move || {
+            for _ in 0..1000 {
+                for i in 0..256 {
+                    thread_trie.fetch_add(&format!("{:016b}", i).into_bytes(), 1);
+                }
+            }
+        }));
+    }
+    for thread in thread_handle {
+        thread.join().unwrap();
+    }
+
+    for i in 0..256 {
+        assert_eq!(trie.get(&format!("{:016b}", i).into_bytes()), Some(NTHREAD * 1000));
+    }
+
+    // sums wrap around modulo usize::MAX, skipping the reserved usize::MAX
+    let key = format!("{:016b}", 0).into_bytes();
+    let max = usize::max_value();
+    assert_eq!(trie.fetch_add(&key, max - 1 - NTHREAD * 1000), Some(NTHREAD * 1000));
+    assert_eq!(trie.get(&key), Some(max - 1));
+    assert_eq!(trie.fetch_add(&key, 1), Some(max - 1));
+    assert_eq!(trie.get(&key), Some(0));
+    assert_eq!(trie.fetch_add(&key, max - 1), Some(0));
+    assert_eq!(trie.fetch_add(&key, 2), Some(max - 1));
+    assert_eq!(trie.get(&key), Some(1));
+    assert_eq!(trie.fetch_add(&key, max), Some(1));
+    assert_eq!(trie.get(&key), Some(1));
+}
@@ -2,7 +2,7 @@
 
 extern crate cchamt;
 
-use cchamt::{Trie, IndexStatus};
+use cchamt::{Trie, ByteTrie, IndexStatus, Change};
 
 #[test]
 fn test_new_trie() {
@@ -96,3 +96,235 @@ fn test_delete() {
 }
 
 
+
+#[test]
+fn test_sparse_insert() {
+    let mut base = Trie::new();
+    let keys: Vec<usize> = (0..2000).map(|i| (i * 40503) % 65536).collect();
+    for &i in &keys {
+        base.insert(i, &format!("{:016b}", i).into_bytes());
+    }
+    for &i in &keys {
+        assert_eq!(base.get(&format!("{:016b}", i).into_bytes()), Some(i));
+    }
+    for i in 0..65536 {
+        if !keys.contains(&i) {
+            assert!(!base.contain(&format!("{:016b}", i).into_bytes()));
+        }
+    }
+    assert_eq!(base.iter().count(), keys.len());
+}
+
+#[test]
+fn test_remove() {
+    let mut base = Trie::new();
+    for i in 0..1000 {
+        assert_eq!(base.insert(i, &format!("{:016b}", i * 61).into_bytes()), 1);
+    }
+    assert_eq!(base.insert(7, &format!("{:016b}", 0).into_bytes()), 0);
+    assert_eq!(base.depth(), 1000);
+
+    for i in 0..500 {
+        assert_eq!(base.remove(&format!("{:016b}", i * 61).into_bytes()), Some(if i == 0 { 7 } else { i }));
+        assert_eq!(base.remove(&format!("{:016b}", i * 61).into_bytes()), None);
+    }
+    assert_eq!(base.depth(), 500);
+    assert_eq!(base.iter().count(), 500);
+    for i in 500..1000 {
+        assert_eq!(base.get(&format!("{:016b}", i * 61).into_bytes()), Some(i));
+    }
+
+    // every child left empty is pruned, down to a bare root
+    for i in 500..1000 {
+        assert_eq!(base.remove(&format!("{:016b}", i * 61).into_bytes()), Some(i));
+    }
+    assert_eq!(base.depth(), 0);
+    assert_eq!(base, Trie::new());
+}
+
+#[test]
+fn test_prefix_match() {
+    let mut routes = Trie::new();
+    routes.insert("default", b"");
+    routes.insert("10/4", b"1010");
+    routes.insert("10.12/8", b"10101100");
+    routes.insert("host", b"1010110011110000");
+
+    assert_eq!(routes.longest_prefix_match(b"1010110011110000"), Some((16, "host")));
+    assert_eq!(routes.longest_prefix_match(b"1010110011110001"), Some((8, "10.12/8")));
+    assert_eq!(routes.longest_prefix_match(b"1010000000000000"), Some((4, "10/4")));
+    assert_eq!(routes.longest_prefix_match(b"0000000000000000"), Some((0, "default")));
+    assert_eq!(routes.all_prefix_matches(b"1010110011110000"),
+               vec![(0, "default"), (4, "10/4"), (8, "10.12/8"), (16, "host")]);
+    assert_eq!(routes.all_prefix_matches(b"10101100"), vec![(0, "default"), (4, "10/4"), (8, "10.12/8")]);
+
+    routes.remove(b"");
+    assert_eq!(routes.longest_prefix_match(b"0000000000000000"), None);
+    assert!(routes.all_prefix_matches(b"0000000000000000").is_empty());
+}
+
+#[test]
+fn test_variable_length_keys() {
+    let mut base = Trie::new();
+    let keys: Vec<String> = (0..7).flat_map(|length| (0..(1 << length)).map(move |i| {
+        let digits = format!("{:08b}", i);
+        digits[(8 - length)..].to_owned()
+    })).collect();
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(base.insert(i, key.as_bytes()), 1);
+    }
+
+    // keys of every length up to 6 coexist, "" and "0" and "00" included
+    assert_eq!(base.depth() as usize, keys.len());
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(base.get(key.as_bytes()), Some(i));
+    }
+    assert_eq!(base.get(b"0000000"), None);
+    let mut entries: Vec<Vec<u8>> = base.iter().map(|a| a.0).collect();
+    let mut expected: Vec<Vec<u8>> = keys.iter().map(|a| a.clone().into_bytes()).collect();
+    entries.sort();
+    expected.sort();
+    assert_eq!(entries, expected);
+
+    assert_eq!(base.all_prefix_matches(b"10110").iter().map(|a| a.0).collect::<Vec<usize>>(), vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(base.longest_prefix_match(b"1011011111"), Some((6, keys.iter().position(|a| a == "101101").unwrap())));
+
+    for key in &keys {
+        assert!(base.remove(key.as_bytes()).is_some());
+    }
+    assert_eq!(base, Trie::new());
+}
+
+#[test]
+fn test_byte_keys() {
+    let mut dictionary = ByteTrie::new();
+    let words = ["", "a", "ab", "abc", "abd", "b", "\u{e9}t\u{e9}", "zebra"];
+    for (i, word) in words.iter().enumerate() {
+        assert_eq!(dictionary.insert(i, word), 1);
+    }
+    let uuid = [0x12u8, 0x3e, 0x45, 0x67, 0xe8, 0x9b, 0x12, 0xd3, 0xa4, 0x56, 0x42, 0x66, 0x14, 0x17, 0x40, 0x00];
+    dictionary.insert(100, &uuid[..]);
+
+    for (i, word) in words.iter().enumerate() {
+        assert_eq!(dictionary.get(word), Some(i));
+    }
+    assert_eq!(dictionary.get(&uuid[..]), Some(100));
+    assert!(!dictionary.contain("abe"));
+    assert!(!dictionary.contain(&[0xffu8, 0x00][..]));
+
+    assert_eq!(dictionary.longest_prefix_match("abcdef"), Some((3, 3)));
+    assert_eq!(dictionary.all_prefix_matches("abx"), vec![(0, 0), (1, 1), (2, 2)]);
+
+    let keys: Vec<Vec<u8>> = dictionary.iter().map(|a| a.0).collect();
+    let mut expected: Vec<Vec<u8>> = words.iter().map(|a| a.as_bytes().to_vec()).collect();
+    expected.push(uuid.to_vec());
+    expected.sort();
+    assert_eq!(keys, expected);
+
+    assert_eq!(dictionary.remove("abc"), Some(3));
+    assert_eq!(dictionary.get("abd"), Some(4));
+    assert_eq!(dictionary.depth(), 8);
+}
+
+#[test]
+fn test_keys_with_prefix() {
+    let mut base = Trie::new();
+    let keys = ["01", "0100", "0101", "010011", "0110", "01101111", "011", "1", "10", "0"];
+    for (i, key) in keys.iter().enumerate() {
+        base.insert(i, key.as_bytes());
+    }
+
+    for prefix in ["", "0", "01", "010", "0100", "01101", "0110111", "1111"].iter() {
+        let mut found: Vec<Vec<u8>> = base.keys_with_prefix(prefix.as_bytes(), None).collect();
+        let mut expected: Vec<Vec<u8>> = keys.iter()
+            .filter(|a| a.starts_with(prefix))
+            .map(|a| a.as_bytes().to_vec())
+            .collect();
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected, "prefix {}", prefix);
+
+        let mut values: Vec<usize> = base.values_with_prefix(prefix.as_bytes(), None).collect();
+        let mut expected: Vec<usize> = (0..keys.len()).filter(|&i| keys[i].starts_with(prefix)).collect();
+        values.sort();
+        expected.sort();
+        assert_eq!(values, expected);
+    }
+    assert_eq!(base.keys_with_prefix(b"01", Some(3)).count(), 3);
+    assert_eq!(base.values_with_prefix(b"", Some(0)).count(), 0);
+
+    let mut identifiers = ByteTrie::new();
+    for (i, word) in ["insert", "insert_batch", "index_base", "get", "get_batch"].iter().enumerate() {
+        identifiers.insert(i, word);
+    }
+    let completions: Vec<Vec<u8>> = identifiers.keys_with_prefix("ins", None).collect();
+    assert_eq!(completions, vec![b"insert".to_vec(), b"insert_batch".to_vec()]);
+    let first: Vec<Vec<u8>> = identifiers.keys_with_prefix("", Some(3)).collect();
+    assert_eq!(first, vec![b"get".to_vec(), b"get_batch".to_vec(), b"index_base".to_vec()]);
+    assert_eq!(identifiers.values_with_prefix("get", Some(1)).collect::<Vec<usize>>(), vec![3]);
+}
+
+#[test]
+fn test_merge() {
+    let mut base = Trie::new();
+    let mut delta = Trie::new();
+    for i in 0..2000usize {
+        base.insert(i, &format!("{:016b}", i * 3).into_bytes());
+        delta.insert(i, &format!("{:016b}", i * 5).into_bytes());
+    }
+    base.insert(1, b"10");
+    delta.insert(2, b"10");
+    delta.insert(3, b"011");
+
+    let mut expected = std::collections::BTreeMap::new();
+    for (key, value) in base.iter() {
+        expected.insert(key, value);
+    }
+    for (key, value) in delta.iter() {
+        let merged = expected.get(&key).map(|&ours| ours * 1000 + value).unwrap_or(value);
+        expected.insert(key, merged);
+    }
+
+    base.merge(delta, |key, ours, theirs| {
+        assert!(key.len() == 2 || key.len() == 16);
+        ours * 1000 + theirs
+    });
+    assert_eq!(base.depth() as usize, expected.len());
+    assert_eq!(base.iter().collect::<std::collections::BTreeMap<_, _>>(), expected);
+    assert_eq!(base.get(b"10"), Some(1002));
+    assert_eq!(base.get(&format!("{:016b}", 15).into_bytes()), Some(5 * 1000 + 3));
+    assert_eq!(base.get(b"011"), Some(3));
+
+    base.merge(Trie::new(), |_, ours, _| ours);
+    assert_eq!(base.depth() as usize, expected.len());
+}
+
+#[test]
+fn test_diff() {
+    let mut old = Trie::new();
+    for i in 0..1000usize {
+        old.insert(i, &format!("{:016b}", i * 3).into_bytes());
+    }
+    old.insert(1, b"10");
+    let mut new = old.clone();
+    assert!(Trie::diff(&old, &new).is_empty());
+    assert!(Trie::diff(&old, &old).is_empty());
+
+    new.insert(7, &format!("{:016b}", 4).into_bytes());
+    new.insert(8, &format!("{:016b}", 6).into_bytes());
+    new.remove(&format!("{:016b}", 9).into_bytes());
+    new.remove(b"10");
+    new.insert(9, b"101");
+
+    let mut changes = Trie::diff(&old, &new);
+    changes.sort_by(|a, b| a.key().cmp(b.key()));
+    assert_eq!(changes, vec![
+        Change::Added(format!("{:016b}", 4).into_bytes(), 7),
+        Change::Changed(format!("{:016b}", 6).into_bytes(), 2, 8),
+        Change::Removed(format!("{:016b}", 9).into_bytes(), 3),
+        Change::Removed(b"10".to_vec(), 1),
+        Change::Added(b"101".to_vec(), 9),
+    ]);
+    assert_eq!(Trie::diff(&new, &old).len(), 5);
+    assert_eq!(Trie::diff(&Trie::new(), &old).len(), 1001);
+}
@@ -10,7 +10,7 @@ use test::Bencher;
 use std::usize;
 use std::collections::HashMap;
 use rand::{Rng, thread_rng};
-use cchamt::MutexContiguousTrie;
+use cchamt::{MutexContiguousTrie, ContiguousTrie, Allocation, Layout, Change};
 
 
 #[test]
@@ -51,3 +51,223 @@ fn test_million_consecutive_insert() {
         assert_eq!(trie.get(&arr[2..]).unwrap(), i);
     }
 }
+
+#[test]
+fn test_lazy_sparse_insert() {
+    let mut trie = ContiguousTrie::<usize>::with_allocation(32, 8, Allocation::Lazy);
+
+    for i in 0..10000 {
+        let str = binary_format!(i * 31);
+        let arr = str.to_owned().into_bytes();
+        trie.insert(i, &arr[2..]);
+    }
+
+    for i in 0..10000 {
+        let str = binary_format!(i * 31);
+        let arr = str.to_owned().into_bytes();
+        assert_eq!(trie.get(&arr[2..]).unwrap(), i);
+    }
+
+    let str = binary_format!(1);
+    assert!(!trie.contain(&str.into_bytes()[2..]));
+    // far below the 2^24 interior nodes of the eager layout
+    assert!(trie.memory_len() < 1000000);
+}
+
+#[test]
+fn test_depth_of() {
+    let mut trie = ContiguousTrie::<usize>::new(16, 4);
+    let mut lazy_trie = ContiguousTrie::<usize>::with_allocation(16, 4, Allocation::Lazy);
+    let mutex_trie = MutexContiguousTrie::<usize>::new(16, 4);
+
+    for i in 0..1000 {
+        let arr = format!("{:016b}", i).into_bytes();
+        trie.insert(i, &arr);
+        lazy_trie.insert(i, &arr);
+        mutex_trie.insert(i, &arr);
+    }
+
+    for i in 0..1000 {
+        let arr = format!("{:016b}", i).into_bytes();
+        assert_eq!(trie.depth_of(&arr), Some(3));
+        assert_eq!(lazy_trie.depth_of(&arr), Some(3));
+        assert_eq!(mutex_trie.depth_of(&arr), Some(3));
+    }
+    let absent = format!("{:016b}", 4321).into_bytes();
+    assert_eq!(trie.depth_of(&absent), None);
+    assert_eq!(lazy_trie.depth_of(&absent), None);
+    assert_eq!(trie.max_depth(), 3);
+    assert_eq!(lazy_trie.max_depth(), 3);
+    assert_eq!(mutex_trie.max_depth(), 3);
+}
+
+#[test]
+fn test_variable_segments() {
+    let mut trie = ContiguousTrie::<usize>::with_segments(&[12, 6, 6], Allocation::Eager);
+    let mut lazy_trie = ContiguousTrie::<usize>::with_segments(&[16, 8, 8], Allocation::Lazy);
+
+    for i in 0..100000 {
+        let arr = format!("{:024b}", i * 13).into_bytes();
+        trie.insert(i, &arr);
+        let arr = binary_format!(i * 13).into_bytes();
+        lazy_trie.insert(i, &arr[2..]);
+    }
+
+    for i in 0..100000 {
+        let arr = format!("{:024b}", i * 13).into_bytes();
+        assert_eq!(trie.get(&arr), Some(i));
+        assert_eq!(trie.depth_of(&arr), Some(2));
+        let arr = binary_format!(i * 13).into_bytes();
+        assert_eq!(lazy_trie.get(&arr[2..]), Some(i));
+    }
+    assert!(!trie.contain(&format!("{:024b}", 14).into_bytes()));
+    assert!(!lazy_trie.contain(&binary_format!(14).into_bytes()[2..]));
+}
+
+#[test]
+fn test_layouts() {
+    let layouts = [Layout::BreadthFirst, Layout::DepthFirst, Layout::VanEmdeBoas];
+    for layout in layouts.iter() {
+        let mut trie = ContiguousTrie::<usize>::with_layout(&[4, 4, 4, 4, 4], *layout);
+
+        for i in 0..100000 {
+            let arr = format!("{:020b}", (i * 7) % 1048576).into_bytes();
+            trie.insert(i, &arr);
+        }
+
+        for i in 0..100000 {
+            let arr = format!("{:020b}", (i * 7) % 1048576).into_bytes();
+            assert_eq!(trie.get(&arr), Some(i));
+            assert_eq!(trie.depth_of(&arr), Some(4));
+        }
+        assert!(!trie.contain(&format!("{:020b}", 700001).into_bytes()));
+        assert_eq!(trie.max_depth(), 4);
+    }
+}
+
+#[test]
+fn test_write_and_open_mmap() {
+    let mut trie = ContiguousTrie::<u64>::with_segments(&[8, 8, 8, 8], Allocation::Lazy);
+    for i in 0..10000u64 {
+        let str = binary_format!(i * 3);
+        trie.insert(i, &str.into_bytes()[2..]);
+    }
+
+    let path = std::env::temp_dir().join("cchamt_test_write_and_open_mmap.bin");
+    trie.write_to(&path).unwrap();
+    let mapped = ContiguousTrie::<u64>::open_mmap(&path).unwrap();
+
+    assert_eq!(mapped.memory_len(), trie.memory_len());
+    for i in 0..10000u64 {
+        let arr = binary_format!(i * 3).into_bytes();
+        assert_eq!(mapped.get(&arr[2..]), Some(i));
+        assert_eq!(mapped.depth_of(&arr[2..]), Some(3));
+    }
+    assert!(!mapped.contain(&binary_format!(1).into_bytes()[2..]));
+
+    // an image of another value type is rejected
+    assert!(ContiguousTrie::<u8>::open_mmap(&path).is_err());
+
+    // verify finds a root slot that claims to be an interior slot on the leaf level
+    mapped.verify().unwrap();
+    let mut image = std::fs::read(&path).unwrap();
+    image[8 * 11 + 4] = 3;
+    std::fs::write(&path, &image).unwrap();
+    assert!(ContiguousTrie::<u64>::open_mmap(&path).unwrap().verify().is_err());
+
+    // open rejects a level count whose header length would overflow
+    let mut huge = image.clone();
+    for byte in &mut huge[8 * 5..8 * 6] {
+        *byte = 0x20;
+    }
+    std::fs::write(&path, &huge).unwrap();
+    assert!(ContiguousTrie::<u64>::open_mmap(&path).is_err());
+
+    // and a truncated one
+    image.truncate(image.len() - 1);
+    std::fs::write(&path, &image).unwrap();
+    assert!(ContiguousTrie::<u64>::open_mmap(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_mutex_batch() {
+    let trie = MutexContiguousTrie::<usize>::new(16, 8);
+    let keys: Vec<Vec<u8>> = (0..1000).rev().map(|i| format!("{:016b}", i * 37).into_bytes()).collect();
+    let entries: Vec<(usize, &[u8])> = keys.iter().enumerate().map(|(i, k)| (i, &k[..])).collect();
+    trie.insert_batch(&entries);
+
+    let mut query: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    let absent = format!("{:016b}", 1).into_bytes();
+    query.push(&absent);
+    let result = trie.get_batch(&query);
+
+    assert_eq!(result.len(), 1001);
+    for i in 0..1000 {
+        assert_eq!(result[i], Some(i));
+        assert_eq!(trie.get(&keys[i]), Some(i));
+    }
+    assert_eq!(result[1000], None);
+}
+
+#[test]
+fn test_merge() {
+    let allocations = [Allocation::Eager, Allocation::Lazy];
+    for ours in allocations.iter() {
+        for theirs in allocations.iter() {
+            let mut base = ContiguousTrie::<usize>::with_allocation(16, 4, *ours);
+            let mut delta = ContiguousTrie::<usize>::with_allocation(16, 4, *theirs);
+            for i in 0..3000 {
+                base.insert(i, &format!("{:016b}", i * 3).into_bytes());
+                delta.insert(i, &format!("{:016b}", i * 7).into_bytes());
+            }
+
+            let mut expected: HashMap<Vec<u8>, usize> = base.iter().collect();
+            for (key, value) in delta.iter() {
+                let merged = expected.get(&key).map(|&a| a + value).unwrap_or(value);
+                expected.insert(key, merged);
+            }
+
+            base.merge(delta, |key, a, b| {
+                assert_eq!(key.len(), 16);
+                a + b
+            });
+            assert_eq!(base.iter().count(), expected.len());
+            for (key, value) in &expected {
+                assert_eq!(base.get(key), Some(*value));
+            }
+            assert_eq!(base.get(&format!("{:016b}", 21).into_bytes()), Some(7 + 3));
+
+            // the copied blocks are ordinary blocks
+            base.insert(0, &format!("{:016b}", 65535).into_bytes());
+            assert_eq!(base.get(&format!("{:016b}", 65535).into_bytes()), Some(0));
+        }
+    }
+}
+
+#[test]
+fn test_diff() {
+    let allocations = [Allocation::Eager, Allocation::Lazy];
+    for old_allocation in allocations.iter() {
+        for new_allocation in allocations.iter() {
+            let mut old = ContiguousTrie::<usize>::with_allocation(16, 8, *old_allocation);
+            let mut new = ContiguousTrie::<usize>::with_allocation(16, 8, *new_allocation);
+            for i in 0..3000 {
+                old.insert(i, &format!("{:016b}", i * 11).into_bytes());
+                new.insert(i, &format!("{:016b}", i * 11).into_bytes());
+            }
+            assert!(ContiguousTrie::diff(&old, &new).is_empty());
+
+            new.insert(1, &format!("{:016b}", 65535).into_bytes());
+            new.insert(5, &format!("{:016b}", 22).into_bytes());
+            new.remove(&format!("{:016b}", 33).into_bytes());
+
+            assert_eq!(ContiguousTrie::diff(&old, &new), vec![
+                Change::Changed(format!("{:016b}", 22).into_bytes(), 2, 5),
+                Change::Removed(format!("{:016b}", 33).into_bytes(), 3),
+                Change::Added(format!("{:016b}", 65535).into_bytes(), 1),
+            ]);
+            assert_eq!(ContiguousTrie::diff(&new, &old)[2], Change::Removed(format!("{:016b}", 65535).into_bytes(), 1));
+        }
+    }
+}
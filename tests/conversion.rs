@@ -0,0 +1,81 @@
+extern crate cchamt;
+
+use cchamt::{Trie, ContiguousTrie, MutexContiguousTrie, RwContiguousTrie, Layout, Allocation};
+
+fn key(i: usize) -> Vec<u8> {
+    format!("{:016b}", i).into_bytes()
+}
+
+fn check_contiguous(trie: &ContiguousTrie<usize>, range: usize) {
+    for i in 0..range {
+        assert_eq!(trie.get(&key(i * 7)), Some(i));
+    }
+    assert_eq!(trie.get(&key(1)), None);
+    assert_eq!(trie.iter().count(), range);
+}
+
+#[test]
+fn test_contiguous_to_locked_and_back() {
+    let mut trie = ContiguousTrie::<usize>::new(16, 4);
+    for i in 0..4096 {
+        trie.insert(i, &key(i * 7));
+    }
+    let memory_len = trie.memory_len();
+
+    // the breadth-first memory is moved as it is
+    let mutex = MutexContiguousTrie::from(trie);
+    for i in 0..4096 {
+        assert_eq!(mutex.get(&key(i * 7)), Some(i));
+    }
+    let rwlock = RwContiguousTrie::from(mutex);
+    assert_eq!(rwlock.stripes(), 1);
+    for i in 0..4096 {
+        assert_eq!(rwlock.get(&key(i * 7)), Some(i));
+    }
+    let trie = ContiguousTrie::from(rwlock);
+    assert_eq!(trie.memory_len(), memory_len);
+    check_contiguous(&trie, 4096);
+}
+
+#[test]
+fn test_reinsert_when_layouts_differ() {
+    let mut trie = ContiguousTrie::<usize>::with_layout(&[8, 4, 4], Layout::VanEmdeBoas);
+    for i in 0..4096 {
+        trie.insert(i, &key(i * 7));
+    }
+    let rwlock = RwContiguousTrie::from(trie);
+    for i in 0..4096 {
+        assert_eq!(rwlock.get(&key(i * 7)), Some(i));
+    }
+
+    let striped = RwContiguousTrie::<usize>::with_stripes(16, 4, 4);
+    for i in 0..4096 {
+        striped.insert(i, &key(i * 7));
+    }
+    check_contiguous(&ContiguousTrie::from(striped), 4096);
+
+    let mut lazy = ContiguousTrie::<usize>::with_allocation(16, 8, Allocation::Lazy);
+    for i in 0..4096 {
+        lazy.insert(i, &key(i * 7));
+    }
+    let mutex = MutexContiguousTrie::from(lazy);
+    for i in 0..4096 {
+        assert_eq!(mutex.get(&key(i * 7)), Some(i));
+    }
+}
+
+#[test]
+fn test_trie_to_contiguous_and_back() {
+    let mut trie = Trie::<usize>::new();
+    for i in 0..4096 {
+        trie.insert(i, &key(i * 7));
+    }
+    let contiguous = ContiguousTrie::from(trie);
+    check_contiguous(&contiguous, 4096);
+
+    let trie = Trie::from(contiguous);
+    for i in 0..4096 {
+        assert_eq!(trie.get(&key(i * 7)), Some(i));
+    }
+    assert_eq!(trie.iter().count(), 4096);
+}
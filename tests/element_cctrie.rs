@@ -0,0 +1,94 @@
+extern crate cchamt;
+
+use std::sync::Arc;
+use std::thread;
+use cchamt::{MutexElementContiguousTrie, RwElementContiguousTrie};
+
+const NTHREAD: usize = 4;
+
+#[test]
+fn test_mutex_element_concurrent_insert() {
+    let trie = Arc::new(MutexElementContiguousTrie::<usize>::new(16, 4));
+    let step = 65536 / NTHREAD;
+
+    let mut thread_handle = vec![];
+    for t_id in 0..NTHREAD {
+        let thread_trie = trie.clone();
+        thread_handle.push(thread::spawn(move || {
+            for i in (t_id * step)..((t_id + 1) * step) {
+                let arr = format!("{:016b}", i).into_bytes();
+                assert_eq!(thread_trie.insert(i, &arr), None);
+            }
+        }));
+    }
+    for thread in thread_handle {
+        thread.join().unwrap();
+    }
+
+    for i in 0..65536 {
+        let arr = format!("{:016b}", i).into_bytes();
+        assert_eq!(trie.get(&arr), Some(i));
+    }
+    let arr = format!("{:016b}", 7).into_bytes();
+    assert_eq!(trie.insert(0, &arr), Some(7));
+}
+
+#[test]
+fn test_rwlock_element_concurrent_insert() {
+    let trie = Arc::new(RwElementContiguousTrie::<usize>::new(16, 8));
+    let step = 65536 / NTHREAD;
+
+    let mut thread_handle = vec![];
+    for t_id in 0..NTHREAD {
+        let thread_trie = trie.clone();
+        thread_handle.push(thread::spawn(move || {
+            // interleave the writers over the key space
+            for i in 0..step {
+                let key = i * NTHREAD + t_id;
+                let arr = format!("{:016b}", key).into_bytes();
+                thread_trie.insert(key, &arr);
+                assert!(thread_trie.contain(&arr));
+            }
+        }));
+    }
+    for thread in thread_handle {
+        thread.join().unwrap();
+    }
+
+    for i in 0..65536 {
+        let arr = format!("{:016b}", i).into_bytes();
+        assert_eq!(trie.get(&arr), Some(i));
+    }
+}
+
+#[test]
+fn test_element_long_keys() {
+    // only the blocks on the paths of the inserted keys are allocated
+    let trie = Arc::new(MutexElementContiguousTrie::<usize>::new(32, 8));
+    let rw_trie = Arc::new(RwElementContiguousTrie::<usize>::new(32, 8));
+
+    let mut thread_handle = vec![];
+    for t_id in 0..NTHREAD {
+        let thread_trie = trie.clone();
+        let thread_rw_trie = rw_trie.clone();
+        thread_handle.push(thread::spawn(move || {
+            for i in 0..1000 {
+                let key = (i * NTHREAD + t_id) * 104729;
+                let arr = format!("{:032b}", key).into_bytes();
+                assert_eq!(thread_trie.insert(key, &arr), None);
+                assert_eq!(thread_rw_trie.insert(key, &arr), None);
+            }
+        }));
+    }
+    for thread in thread_handle {
+        thread.join().unwrap();
+    }
+
+    for i in 0..(1000 * NTHREAD) {
+        let arr = format!("{:032b}", i * 104729).into_bytes();
+        assert_eq!(trie.get(&arr), Some(i * 104729));
+        assert_eq!(rw_trie.get(&arr), Some(i * 104729));
+    }
+    assert!(!trie.contain(&format!("{:032b}", 1).into_bytes()));
+    assert!(!rw_trie.contain(&format!("{:032b}", u32::max_value()).into_bytes()));
+}
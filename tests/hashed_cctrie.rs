@@ -0,0 +1,72 @@
+extern crate cchamt;
+
+use std::collections::HashMap;
+use cchamt::{HashedTrie, Trie, ContiguousTrie};
+
+#[test]
+fn test_hashed_trie_like_hashmap() {
+    let mut trie = HashedTrie::new();
+    let mut contiguous = HashedTrie::contiguous(16, 8);
+    let mut expected = HashMap::new();
+
+    for i in 0..5000 {
+        let key = format!("key {}", i % 3000);
+        let previous = expected.insert(key.clone(), i);
+        assert_eq!(trie.insert(key.clone(), i), previous);
+        assert_eq!(contiguous.insert(key, i), previous);
+    }
+    assert_eq!(trie.len(), expected.len());
+    assert_eq!(contiguous.len(), expected.len());
+
+    for (key, value) in &expected {
+        assert_eq!(trie.get(key.as_str()), Some(value));
+        assert_eq!(contiguous.get(key.as_str()), Some(value));
+    }
+    assert!(!trie.contains_key("key 3000"));
+    assert!(!contiguous.contains_key("key 3000"));
+
+    for i in 0..1000 {
+        let key = format!("key {}", i);
+        assert_eq!(trie.remove(key.as_str()), expected.remove(&key));
+        assert_eq!(contiguous.remove(key.as_str()), Some(i + 3000));
+        assert_eq!(trie.remove(key.as_str()), None);
+    }
+    assert_eq!(trie.len(), 2000);
+    assert_eq!(contiguous.len(), 2000);
+    assert_eq!(trie.iter().count(), 2000);
+
+    *trie.get_mut("key 2999").unwrap() = 0;
+    assert_eq!(trie.get("key 2999"), Some(&0));
+}
+
+#[test]
+fn test_hashed_trie_collisions() {
+    // 4 hash bits for 1000 keys, every bucket holds many keys
+    let mut trie = HashedTrie::with_backend(Trie::new(), 4);
+    let mut contiguous = HashedTrie::with_backend(ContiguousTrie::new(4, 4), 4);
+    for i in 0..1000u64 {
+        assert_eq!(trie.insert(i, i * 2), None);
+        assert_eq!(contiguous.insert(i, i * 2), None);
+    }
+    for i in 0..1000u64 {
+        assert_eq!(trie.get(&i), Some(&(i * 2)));
+        assert_eq!(contiguous.get(&i), Some(&(i * 2)));
+    }
+    assert_eq!(trie.remove(&500), Some(1000));
+    assert_eq!(trie.get(&500), None);
+    assert_eq!(trie.get(&501), Some(&1002));
+
+    // emptied buckets leave the trie and are reused by the next hashes
+    for i in 0..1000u64 {
+        assert_eq!(contiguous.remove(&i), Some(i * 2));
+    }
+    assert!(contiguous.is_empty());
+    assert_eq!(contiguous.iter().count(), 0);
+    for i in 0..1000u64 {
+        assert_eq!(contiguous.insert(i, i * 3), None);
+    }
+    for i in 0..1000u64 {
+        assert_eq!(contiguous.get(&i), Some(&(i * 3)));
+    }
+    assert_eq!(contiguous.len(), 1000);
+}
@@ -50,3 +50,20 @@ fn test_lockfree_million_consecutive_insert() {
         }
     }
 }
+
+#[test]
+fn test_lockfree_remove() {
+    let trie = LockfreeTrie::<u64, u64>::new();
+    for i in 0..10000 {
+        trie.insert(i, i + 1);
+    }
+    for i in (0..10000).filter(|i| i % 3 == 0) {
+        assert_eq!(trie.remove(&i), Some(i + 1));
+        assert_eq!(trie.remove(&i), None);
+    }
+    for i in 0..10000 {
+        assert_eq!(trie.lookup(&i).cloned(), if i % 3 == 0 { None } else { Some(i + 1) });
+    }
+    trie.insert(3, 7);
+    assert_eq!(trie.lookup(&3), Some(&7));
+}
@@ -0,0 +1,135 @@
+extern crate cchamt;
+
+use std::sync::Arc;
+use std::thread;
+use cchamt::{Map, RemovableMap, ConcurrentMap, Trie, ContiguousTrie, PersistentTrie, LockfreeTrie, MutexContiguousTrie, RwContiguousTrie,
+             MutexElementContiguousTrie, RwElementContiguousTrie, SeqlockContiguousTrie, AtomicContiguousTrie};
+
+const NTHREAD: usize = 4;
+
+fn check_map<M: Map<[u8], usize>>(mut map: M) {
+    for i in 0..4096 {
+        let arr = format!("{:016b}", i * 13).into_bytes();
+        map.insert(&arr, i);
+    }
+    for i in 0..4096 {
+        let arr = format!("{:016b}", i * 13).into_bytes();
+        assert_eq!(map.get(&arr), Some(i));
+        assert!(map.contains(&arr));
+    }
+    let arr = format!("{:016b}", 1).into_bytes();
+    assert_eq!(map.get(&arr), None);
+    assert!(!map.contains(&arr));
+
+    // insert overwrites
+    let arr = format!("{:016b}", 13).into_bytes();
+    map.insert(&arr, 7);
+    assert_eq!(map.get(&arr), Some(7));
+}
+
+fn check_removable_map<M: RemovableMap<[u8], usize>>(mut map: M) {
+    for i in 0..1024 {
+        let arr = format!("{:016b}", i * 13).into_bytes();
+        map.insert(&arr, i);
+    }
+    for i in (0..1024).filter(|i| i % 2 == 0) {
+        let arr = format!("{:016b}", i * 13).into_bytes();
+        assert_eq!(map.remove(&arr), Some(i));
+        assert_eq!(map.remove(&arr), None);
+    }
+    for i in 0..1024 {
+        let arr = format!("{:016b}", i * 13).into_bytes();
+        assert_eq!(map.get(&arr), if i % 2 == 0 { None } else { Some(i) });
+    }
+}
+
+fn check_concurrent_map<M: ConcurrentMap<[u8], usize> + Send + Sync + 'static>(map: M) {
+    let map = Arc::new(map);
+    let step = 65536 / NTHREAD;
+
+    let mut thread_handle = vec![];
+    for t_id in 0..NTHREAD {
+        let thread_map = map.clone();
+        thread_handle.push(thread::spawn(move || {
+            for i in (t_id * step)..((t_id + 1) * step) {
+                let arr = format!("{:016b}", i).into_bytes();
+                thread_map.insert(&arr, i);
+            }
+        }));
+    }
+    for thread in thread_handle {
+        thread.join().unwrap();
+    }
+
+    for i in 0..65536 {
+        let arr = format!("{:016b}", i).into_bytes();
+        assert_eq!(map.get(&arr), Some(i));
+        assert!(map.contains(&arr));
+    }
+}
+
+#[test]
+fn test_map_trie() {
+    check_map(Trie::<usize>::new());
+}
+
+#[test]
+fn test_map_contiguous_trie() {
+    check_map(ContiguousTrie::<usize>::new(16, 4));
+}
+
+#[test]
+fn test_removable_map() {
+    check_removable_map(Trie::<usize>::new());
+    check_removable_map(PersistentTrie::<usize>::new());
+    check_removable_map(ContiguousTrie::<usize>::new(16, 4));
+}
+
+#[test]
+fn test_map_concurrent_tries() {
+    check_map(MutexContiguousTrie::<usize>::new(16, 4));
+    check_map(RwContiguousTrie::<usize>::with_stripes(16, 4, 4));
+    check_map(MutexElementContiguousTrie::<usize>::new(16, 4));
+    check_map(RwElementContiguousTrie::<usize>::new(16, 4));
+    check_map(SeqlockContiguousTrie::<usize>::new(16, 4));
+    check_map(AtomicContiguousTrie::new(16, 4));
+}
+
+#[test]
+fn test_concurrent_map() {
+    check_concurrent_map(MutexContiguousTrie::<usize>::new(16, 8));
+    check_concurrent_map(RwContiguousTrie::<usize>::with_stripes(16, 8, 16));
+    check_concurrent_map(MutexElementContiguousTrie::<usize>::new(16, 8));
+    check_concurrent_map(RwElementContiguousTrie::<usize>::new(16, 8));
+    check_concurrent_map(SeqlockContiguousTrie::<usize>::new(16, 8));
+    check_concurrent_map(AtomicContiguousTrie::new(16, 8));
+}
+
+#[test]
+fn test_map_lockfree_trie() {
+    let mut trie = LockfreeTrie::<u64, u64>::new();
+    for i in 0..4096 {
+        Map::insert(&mut trie, &i, i + 1);
+    }
+    for i in 0..4096 {
+        assert_eq!(Map::get(&trie, &i), Some(i + 1));
+    }
+    assert!(!Map::contains(&trie, &4096));
+
+    let trie = Arc::new(LockfreeTrie::<u64, u64>::new());
+    let mut thread_handle = vec![];
+    for t_id in 0..NTHREAD as u64 {
+        let thread_trie = trie.clone();
+        thread_handle.push(thread::spawn(move || {
+            for i in (t_id * 16384)..((t_id + 1) * 16384) {
+                ConcurrentMap::insert(&*thread_trie, &i, i + 1);
+            }
+        }));
+    }
+    for thread in thread_handle {
+        thread.join().unwrap();
+    }
+    for i in 0..(NTHREAD as u64 * 16384) {
+        assert_eq!(ConcurrentMap::get(&*trie, &i), Some(i + 1));
+    }
+}
@@ -0,0 +1,55 @@
+extern crate cchamt;
+
+use cchamt::{BitKey, FromBits, Hashed, ContiguousTrie, LockfreeTrie};
+
+#[test]
+fn test_bit_key_encoding() {
+    assert_eq!(5u32.to_bits(8), b"00000101".to_vec());
+    assert_eq!(u32::from_bits(b"00000101"), 5);
+    assert_eq!(u64::from_bits(&u64::max_value().to_bits(64)), u64::max_value());
+    assert_eq!(b"101"[..].to_bits(4), b"0101".to_vec());
+
+    let hashed = Hashed("some key").to_bits(16);
+    assert_eq!(hashed.len(), 16);
+    assert_eq!(hashed, Hashed("some key").to_bits(16));
+}
+
+#[test]
+#[should_panic]
+fn test_bit_key_too_long() {
+    256u32.to_bits(8);
+}
+
+#[test]
+fn test_contiguous_iter() {
+    let mut trie = ContiguousTrie::<usize>::with_segments(&[8, 4, 4], cchamt::Allocation::Lazy);
+    let mut keys: Vec<usize> = (0..1000).map(|i| (i * 7919) % 65536).collect();
+    for &i in &keys {
+        trie.insert(i, &i.to_bits(16));
+    }
+
+    keys.sort();
+    let entries: Vec<(Vec<u8>, usize)> = trie.iter().collect();
+    assert_eq!(entries.len(), keys.len());
+    for (&(ref key, value), &i) in entries.iter().zip(keys.iter()) {
+        assert_eq!(usize::from_bits(key), i);
+        assert_eq!(value, i);
+    }
+}
+
+#[test]
+fn test_contiguous_to_lockfree() {
+    // build single threaded, then publish in a trie keyed by the integers themselves
+    let mut trie = ContiguousTrie::<u64>::new(16, 4);
+    for i in 0..4096u64 {
+        trie.insert(i * 2, &(i * 3).to_bits(16));
+    }
+
+    let published = LockfreeTrie::<u64, u64>::new();
+    for (key, value) in trie.iter() {
+        published.insert(u64::from_bits(&key), value);
+    }
+    for i in 0..4096u64 {
+        assert_eq!(published.lookup(&(i * 3)), Some(&(i * 2)));
+    }
+}
@@ -0,0 +1,115 @@
+extern crate cchamt;
+
+use std::sync::Arc;
+use std::thread;
+use cchamt::{PersistentTrie, Map, Change};
+
+fn key(i: usize) -> Vec<u8> {
+    format!("{:016b}", i).into_bytes()
+}
+
+#[test]
+fn test_versions_are_independent() {
+    let mut versions = vec![PersistentTrie::<usize>::new()];
+    for i in 0..1000 {
+        let next = versions.last().unwrap().insert(i, &key(i * 61));
+        versions.push(next);
+    }
+
+    for (n, version) in versions.iter().enumerate() {
+        assert_eq!(version.depth(), n as u32);
+        for i in 0..1000 {
+            assert_eq!(version.get(&key(i * 61)), if i < n { Some(i) } else { None });
+        }
+    }
+
+    let last = versions.last().unwrap();
+    let overwritten = last.insert(7, &key(0));
+    assert_eq!(overwritten.depth(), 1000);
+    assert_eq!(overwritten.get(&key(0)), Some(7));
+    assert_eq!(last.get(&key(0)), Some(0));
+
+    let removed = last.remove(&key(61));
+    assert_eq!(removed.depth(), 999);
+    assert!(!removed.contain(&key(61)));
+    assert!(last.contain(&key(61)));
+    assert_eq!(removed.iter().count(), 999);
+}
+
+#[test]
+fn test_structural_sharing() {
+    let base = PersistentTrie::<usize>::new()
+        .insert(1, b"0000000000000000")
+        .insert(2, b"1000000000000000");
+    let next = base.insert(3, b"1000000000000001");
+
+    // only the path to the new key is copied
+    assert!(!base.ptr_eq(&next));
+    assert!(base.get_sub_trie(b"0000").unwrap().ptr_eq(&next.get_sub_trie(b"0000").unwrap()));
+    assert!(!base.get_sub_trie(b"1000").unwrap().ptr_eq(&next.get_sub_trie(b"1000").unwrap()));
+    assert!(base.get_sub_trie(b"1000000000000000").unwrap().ptr_eq(&next.get_sub_trie(b"1000000000000000").unwrap()));
+
+    // removing an absent key copies nothing, removing every key prunes down to an empty trie
+    assert!(next.remove(b"1111111111111111").ptr_eq(&next));
+    let empty = next.remove(b"0000000000000000").remove(b"1000000000000000").remove(b"1000000000000001");
+    assert_eq!(empty, PersistentTrie::new());
+}
+
+#[test]
+fn test_readers_keep_old_versions() {
+    let mut trie = PersistentTrie::<usize>::new();
+    for i in 0..1000 {
+        Map::insert(&mut trie, &key(i), i);
+    }
+    let published = Arc::new(trie.clone());
+
+    let mut thread_handle = vec![];
+    for _ in 0..4 {
+        let version = published.clone();
+        thread_handle.push(thread::spawn(move || {
+            for i in 0..1000 {
+                assert_eq!(version.get(&key(i)), Some(i));
+            }
+        }));
+    }
+    for i in 0..1000 {
+        trie = trie.remove(&key(i));
+    }
+    for thread in thread_handle {
+        thread.join().unwrap();
+    }
+    assert_eq!(trie.depth(), 0);
+    assert_eq!(published.depth(), 1000);
+}
+
+#[test]
+fn test_variable_length_keys() {
+    let trie = PersistentTrie::<usize>::new()
+        .insert(1, b"01")
+        .insert(2, b"0100")
+        .insert(3, b"010011");
+    assert_eq!(trie.get(b"01"), Some(1));
+    assert_eq!(trie.get(b"0100"), Some(2));
+    assert_eq!(trie.get(b"010011"), Some(3));
+    assert_eq!(trie.get(b"010"), None);
+    assert_eq!(trie.iter().count(), 3);
+    assert_eq!(trie.remove(b"0100").remove(b"01").remove(b"010011"), PersistentTrie::new());
+}
+
+#[test]
+fn test_diff_versions() {
+    let mut old = PersistentTrie::new();
+    for i in 0..1000 {
+        old = old.insert(i, &key(i * 61));
+    }
+    let new = old.insert(1, &key(61)).insert(7, &key(1)).remove(&key(122));
+
+    assert!(PersistentTrie::diff(&old, &old).is_empty());
+    let mut changes = PersistentTrie::diff(&old, &new);
+    changes.sort_by(|a, b| a.key().cmp(b.key()));
+    assert_eq!(changes, vec![
+        Change::Added(key(1), 7),
+        Change::Removed(key(122), 2),
+    ]);
+    assert_eq!(PersistentTrie::diff(&new, &old.insert(3, &key(0)))[0], Change::Changed(key(0), 0, 3));
+}
@@ -0,0 +1,97 @@
+extern crate cchamt;
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+use cchamt::RwContiguousTrie;
+
+const NTHREAD: usize = 4;
+
+#[test]
+fn test_striped_concurrent_insert() {
+    let trie = Arc::new(RwContiguousTrie::<usize>::with_stripes(24, 8, 16));
+    assert_eq!(trie.stripes(), 16);
+    let step = 25000;
+
+    let mut thread_handle = vec![];
+    for t_id in 0..NTHREAD {
+        let thread_trie = trie.clone();
+        thread_handle.push(thread::spawn(move || {
+            // spread the keys of every writer over the whole key space
+            for i in (t_id * step)..((t_id + 1) * step) {
+                let arr = format!("{:024b}", (i * 167) % 16777216).into_bytes();
+                thread_trie.insert(i, &arr);
+            }
+        }));
+    }
+    for thread in thread_handle {
+        thread.join().unwrap();
+    }
+
+    for i in 0..(NTHREAD * step) {
+        let arr = format!("{:024b}", (i * 167) % 16777216).into_bytes();
+        assert_eq!(trie.get(&arr), Some(i));
+        assert_eq!(trie.depth_of(&arr), Some(2));
+    }
+    assert!(!trie.contain(&format!("{:024b}", 1).into_bytes()));
+    assert_eq!(trie.max_depth(), 2);
+}
+
+#[test]
+fn test_concurrent_duplicate_insert_is_linearizable() {
+    let trie = Arc::new(RwContiguousTrie::<usize>::new(16, 8));
+    let barrier = Arc::new(Barrier::new(NTHREAD));
+    let keys = 4096;
+
+    let mut thread_handle = vec![];
+    for t_id in 0..NTHREAD {
+        let thread_trie = trie.clone();
+        let barrier = barrier.clone();
+        thread_handle.push(thread::spawn(move || {
+            barrier.wait();
+            // every writer inserts every key, only the first insert of a key sees no previous value
+            let mut first = vec![];
+            for round in 0..8 {
+                for i in 0..keys {
+                    let arr = format!("{:016b}", i).into_bytes();
+                    let old = thread_trie.insert(t_id * 8 + round, &arr);
+                    if old.is_none() {
+                        first.push(i);
+                    }
+                }
+            }
+            first
+        }));
+    }
+
+    let mut first_count = vec![0; keys];
+    for thread in thread_handle {
+        for i in thread.join().unwrap() {
+            first_count[i] += 1;
+        }
+    }
+
+    for i in 0..keys {
+        assert_eq!(first_count[i], 1);
+        let arr = format!("{:016b}", i).into_bytes();
+        assert!(trie.get(&arr).unwrap() < NTHREAD * 8);
+    }
+}
+
+#[test]
+fn test_striped_batch() {
+    let trie = RwContiguousTrie::<usize>::with_stripes(16, 8, 4);
+    let keys: Vec<Vec<u8>> = (0..1000).map(|i| format!("{:016b}", (i * 641) % 65536).into_bytes()).collect();
+    let mut entries: Vec<(usize, &[u8])> = keys.iter().enumerate().map(|(i, k)| (i, &k[..])).collect();
+    // a key repeated later in the batch wins
+    entries.push((5000, &keys[3][..]));
+    trie.insert_batch(&entries);
+
+    let query: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    let result = trie.get_batch(&query);
+    for i in 0..1000 {
+        let expected = if i == 3 { 5000 } else { i };
+        assert_eq!(result[i], Some(expected));
+        assert_eq!(trie.get(&keys[i]), Some(expected));
+    }
+    assert_eq!(trie.get_batch(&[&format!("{:016b}", 1).into_bytes()[..]]), vec![None]);
+}
@@ -0,0 +1,59 @@
+extern crate cchamt;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use cchamt::SeqlockContiguousTrie;
+
+const NTHREAD: usize = 4;
+
+#[test]
+fn test_seqlock_insert_get() {
+    let trie = SeqlockContiguousTrie::<usize>::new(32, 8);
+
+    for i in 0..100000 {
+        let arr = format!("{:032b}", i).into_bytes();
+        assert_eq!(trie.insert(i, &arr), None);
+    }
+    for i in 0..100000 {
+        let arr = format!("{:032b}", i).into_bytes();
+        assert_eq!(trie.get(&arr), Some(i));
+    }
+    let arr = format!("{:032b}", 1 << 30).into_bytes();
+    assert!(!trie.contain(&arr));
+    assert_eq!(trie.insert(1, &format!("{:032b}", 0).into_bytes()), Some(0));
+}
+
+#[test]
+fn test_seqlock_readers_during_writes() {
+    let trie = Arc::new(SeqlockContiguousTrie::<(usize, usize)>::new(16, 8));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let mut thread_handle = vec![];
+    for _ in 0..NTHREAD {
+        let thread_trie = trie.clone();
+        let done = done.clone();
+        thread_handle.push(thread::spawn(move || {
+            // the writer always stores a pair of equal halves, a torn read would break that
+            while !done.load(Ordering::Relaxed) {
+                for i in 0..256 {
+                    let arr = format!("{:016b}", i).into_bytes();
+                    if let Some((a, b)) = thread_trie.get(&arr) {
+                        assert_eq!(a, b);
+                    }
+                }
+            }
+        }));
+    }
+
+    for round in 0..200 {
+        for i in 0..256 {
+            let arr = format!("{:016b}", i).into_bytes();
+            trie.insert((round, round), &arr);
+        }
+    }
+    done.store(true, Ordering::Relaxed);
+    for thread in thread_handle {
+        thread.join().unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+extern crate cchamt;
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::thread;
+use cchamt::{TrieSet, ContiguousTrieSet, LockfreeTrieSet, Allocation};
+
+fn key(i: usize) -> Vec<u8> {
+    format!("{:016b}", i).into_bytes()
+}
+
+#[test]
+fn test_trie_set() {
+    let mut set = TrieSet::new();
+    assert!(set.is_empty());
+    for i in 0..1000 {
+        assert!(set.insert(&key(i * 7)));
+    }
+    assert!(!set.insert(&key(0)));
+    assert!(set.insert(b"101"));
+    assert_eq!(set.len(), 1001);
+
+    assert!(set.contains(&key(7)));
+    assert!(!set.contains(&key(8)));
+    assert!(set.contains(b"101"));
+    assert!(!set.contains(b"10"));
+
+    assert!(set.remove(&key(7)));
+    assert!(!set.remove(&key(7)));
+    assert!(!set.contains(&key(7)));
+    assert_eq!(set.len(), 1000);
+    assert_eq!(set.iter().count(), 1000);
+}
+
+#[test]
+fn test_trie_set_algebra() {
+    let mut a = TrieSet::new();
+    let mut b = TrieSet::new();
+    for i in 0..3000 {
+        if i % 2 == 0 {
+            a.insert(&key(i));
+        }
+        if i % 3 == 0 {
+            b.insert(&key(i));
+        }
+    }
+    a.insert(b"1");
+    b.insert(b"01");
+
+    let a_keys: BTreeSet<Vec<u8>> = a.iter().collect();
+    let b_keys: BTreeSet<Vec<u8>> = b.iter().collect();
+
+    let union = a.union(&b);
+    assert_eq!(union.iter().collect::<BTreeSet<_>>(), &a_keys | &b_keys);
+    assert_eq!(union.len(), (&a_keys | &b_keys).len());
+
+    let intersection = a.intersection(&b);
+    assert_eq!(intersection.iter().collect::<BTreeSet<_>>(), &a_keys & &b_keys);
+    assert_eq!(intersection.len(), 500);
+
+    let difference = a.difference(&b);
+    assert_eq!(difference.iter().collect::<BTreeSet<_>>(), &a_keys - &b_keys);
+    assert_eq!(difference.len(), 1001);
+
+    assert!(a.intersection(&TrieSet::new()).is_empty());
+    assert_eq!(a.union(&TrieSet::new()), a);
+    assert_eq!(difference.union(&intersection), a);
+}
+
+#[test]
+fn test_contiguous_trie_set_algebra() {
+    for &(left, right) in &[(Allocation::Eager, Allocation::Eager), (Allocation::Lazy, Allocation::Eager),
+                            (Allocation::Eager, Allocation::Lazy), (Allocation::Lazy, Allocation::Lazy)] {
+        let mut a = ContiguousTrieSet::with_allocation(16, 8, left);
+        let mut b = ContiguousTrieSet::with_allocation(16, 8, right);
+        for i in 0..3000 {
+            if i % 2 == 0 {
+                a.insert(&key(i));
+            }
+            if i % 3 == 0 {
+                b.insert(&key(i * 11));
+            }
+        }
+        assert!(!a.insert(&key(0)));
+        assert!(a.remove(&key(2)));
+        assert!(!a.remove(&key(2)));
+        assert!(!a.contains(&key(2)));
+        assert!(a.contains(&key(4)));
+
+        let a_keys: BTreeSet<Vec<u8>> = a.iter().collect();
+        let b_keys: BTreeSet<Vec<u8>> = b.iter().collect();
+        assert_eq!(a_keys.len(), a.len());
+        assert_eq!(b_keys.len(), b.len());
+
+        let union = a.union(&b);
+        assert_eq!(union.iter().collect::<BTreeSet<_>>(), &a_keys | &b_keys);
+        assert_eq!(union.len(), (&a_keys | &b_keys).len());
+
+        let intersection = a.intersection(&b);
+        assert_eq!(intersection.iter().collect::<BTreeSet<_>>(), &a_keys & &b_keys);
+        assert_eq!(intersection.len(), (&a_keys & &b_keys).len());
+
+        let mut difference = a.difference(&b);
+        assert_eq!(difference.iter().collect::<BTreeSet<_>>(), &a_keys - &b_keys);
+        assert_eq!(difference.len(), (&a_keys - &b_keys).len());
+
+        // the combined sets are regular sets
+        assert!(difference.insert(&key(65535)));
+        assert!(difference.contains(&key(65535)));
+    }
+}
+
+#[test]
+fn test_lockfree_trie_set_algebra() {
+    let a = LockfreeTrieSet::new();
+    let b = LockfreeTrieSet::new();
+    for i in 0..1000u64 {
+        if i % 2 == 0 {
+            a.insert(i);
+        }
+        if i % 3 == 0 {
+            b.insert(i);
+        }
+    }
+    assert!(a.remove(&0));
+    assert!(!a.remove(&0));
+    assert!(!a.contains(&0));
+    assert!(a.contains(&2));
+
+    let a_keys: BTreeSet<u64> = a.iter().collect();
+    let b_keys: BTreeSet<u64> = b.iter().collect();
+    assert_eq!(a_keys.len(), 499);
+    assert_eq!(b_keys.len(), 334);
+
+    assert_eq!(a.union(&b).iter().collect::<BTreeSet<_>>(), &a_keys | &b_keys);
+    assert_eq!(a.intersection(&b).iter().collect::<BTreeSet<_>>(), &a_keys & &b_keys);
+    assert_eq!(a.difference(&b).iter().collect::<BTreeSet<_>>(), &a_keys - &b_keys);
+
+    // a removed key can come back
+    a.insert(0);
+    assert!(a.contains(&0));
+}
+
+#[test]
+fn test_lockfree_trie_set_shared() {
+    let set = Arc::new(LockfreeTrieSet::new());
+    let mut thread_handle = vec![];
+    for t_id in 0..4u64 {
+        let thread_set = set.clone();
+        thread_handle.push(thread::spawn(move || {
+            for i in (t_id * 10000)..((t_id + 1) * 10000) {
+                thread_set.insert(i);
+            }
+            // every thread removes the odd keys of its own range
+            for i in (t_id * 10000)..((t_id + 1) * 10000) {
+                if i % 2 == 1 {
+                    assert!(thread_set.remove(&i));
+                }
+            }
+        }));
+    }
+    for thread in thread_handle {
+        thread.join().unwrap();
+    }
+
+    for i in 0..40000u64 {
+        assert_eq!(set.contains(&i), i % 2 == 0);
+    }
+    assert_eq!(set.iter().count(), 20000);
+}